@@ -0,0 +1,46 @@
+#![no_main]
+
+use std::io;
+use std::sync::OnceLock;
+
+use easy_logging::{EasyLogger, Encoder, FileSink, JsonEncoder, LogfmtEncoder, LoggingConfig, TargetColumn, TextEncoder};
+use libfuzzer_sys::fuzz_target;
+use log::{Level, Log, Record};
+
+// Passes arbitrary messages, targets and source locations through all encoders
+fuzz_target!(|data: &[u8]| {
+    static LOGGER: OnceLock<EasyLogger> = OnceLock::new();
+
+    let logger = LOGGER.get_or_init(|| {
+        let text = TextEncoder::new().highlight_values().target(TargetColumn::new().abbreviate().max_width(10));
+
+        LoggingConfig::new("fuzz", Level::Trace)
+            .console_encoder(text)
+            .console_writers(io::sink(), io::sink(), true)
+            .file(sink(JsonEncoder::new()))
+            .file(sink(LogfmtEncoder::new()))
+            .max_message_length(1024)
+            .logger()
+    });
+
+    let data = String::from_utf8_lossy(data);
+    let mut parts = data.splitn(4, '\u{1}');
+    let (target, file, line, message) = (
+        parts.next().unwrap_or_default(), parts.next().unwrap_or_default(),
+        parts.next().and_then(|line| line.parse().ok()), parts.next().unwrap_or_default(),
+    );
+
+    for level in [Level::Error, Level::Warn, Level::Info, Level::Debug, Level::Trace] {
+        logger.log(&Record::builder()
+            .level(level)
+            .target(target)
+            .file(Some(file))
+            .line(line)
+            .args(format_args!("{message}"))
+            .build());
+    }
+});
+
+fn sink<E: Encoder + 'static>(encoder: E) -> FileSink {
+    FileSink::new("/dev/null").encoder(encoder)
+}
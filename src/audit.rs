@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+use log::{Level, Record};
+
+use crate::encoder::write_logfmt_value;
+use crate::pipeline;
+
+/// The target of audit records emitted by [`audit!`](crate::audit!).
+pub const AUDIT_TARGET: &str = "easy_logging::audit";
+
+pub(crate) fn is_audit(target: &str) -> bool {
+    target == AUDIT_TARGET
+}
+
+// Audit records bypass the level filters (including the global max level), so the record is passed
+// to the logger directly instead of the log macros.
+#[doc(hidden)]
+pub fn emit(module_path: &'static str, file: &'static str, line: u32, fields: &[(&str, &dyn Display)]) {
+    // Audit records may be emitted by any code, so the values are formatted defensively like the
+    // messages of regular records
+    let values: Vec<String> = fields.iter().map(|(_, value)| {
+        pipeline::format_safely(format_args!("{value}"))
+    }).collect();
+    let key_values: Vec<(&str, &str)> = fields.iter().zip(&values)
+        .map(|((name, _), value)| (*name, value.as_str()))
+        .collect();
+
+    // The fields are rendered in the message as well to not lose them in the sinks which don't
+    // support structured data
+    let mut message = Vec::new();
+    for (index, (name, value)) in key_values.iter().enumerate() {
+        if index != 0 {
+            message.push(b' ');
+        }
+        message.extend_from_slice(name.as_bytes());
+        message.push(b'=');
+        let _ = write_logfmt_value(&mut message, value);
+    }
+    let message = String::from_utf8_lossy(&message);
+
+    log::logger().log(&Record::builder()
+        .level(Level::Info)
+        .target(AUDIT_TARGET)
+        .module_path_static(Some(module_path))
+        .file_static(Some(file))
+        .line(Some(line))
+        .key_values(&key_values)
+        .args(format_args!("{message}"))
+        .build());
+}
+
+/// Emits an audit record with the specified fields: `audit!(action = "delete_user", actor = id)`.
+///
+/// Audit records are always emitted regardless of the configured levels and are never suppressed by
+/// burst limits. They are written only to the audit sinks (see
+/// [`LoggingConfig::audit()`](crate::LoggingConfig::audit)) or to the regular ones if there are no
+/// audit sinks configured.
+#[macro_export]
+macro_rules! audit {
+    ($($name:ident = $value:expr),+ $(,)?) => {
+        $crate::__audit(module_path!(), file!(), line!(), &[
+            $((stringify!($name), &$value as &dyn ::std::fmt::Display)),+
+        ])
+    };
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::Level;
+
+use crate::clock;
+
+// Holds recent encoded Debug/Trace records to emit them only when an error occurs
+pub(crate) struct ErrorContextBuffer {
+    window: Duration,
+    capacity: usize,
+    // Records with their monotonic time
+    records: Mutex<VecDeque<(Duration, Level, Vec<u8>)>>,
+}
+
+impl ErrorContextBuffer {
+    pub fn new(window: Duration, capacity: usize) -> ErrorContextBuffer {
+        ErrorContextBuffer {
+            window, capacity,
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn push(&self, level: Level, data: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut records = self.records.lock().unwrap();
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back((clock::monotonic(), level, data));
+    }
+
+    // Returns all records which fit into the window without taking them
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    pub fn snapshot(&self) -> Vec<Vec<u8>> {
+        let now = clock::monotonic();
+
+        self.records.lock().unwrap().iter()
+            .filter(|(time, _, _)| now.saturating_sub(*time) <= self.window)
+            .map(|(_, _, data)| data.clone())
+            .collect()
+    }
+
+    // Takes all records which fit into the window
+    pub fn take(&self) -> Vec<(Level, Vec<u8>)> {
+        let now = clock::monotonic();
+        let records = std::mem::take(&mut *self.records.lock().unwrap());
+
+        records.into_iter()
+            .filter(|(time, _, _)| now.saturating_sub(*time) <= self.window)
+            .map(|(_, level, data)| (level, data))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::tests::freeze;
+
+    use super::*;
+
+    fn push(buffer: &ErrorContextBuffer, records: &[&str]) {
+        for record in records {
+            buffer.push(Level::Debug, record.as_bytes().to_vec());
+        }
+    }
+
+    fn take(buffer: &ErrorContextBuffer) -> Vec<String> {
+        buffer.take().into_iter().map(|(_, data)| String::from_utf8(data).unwrap()).collect()
+    }
+
+    #[test]
+    fn buffering() {
+        let _clock = freeze();
+        let buffer = ErrorContextBuffer::new(Duration::from_secs(10), 10);
+
+        push(&buffer, &["first", "second"]);
+        buffer.push(Level::Trace, b"third".to_vec());
+        assert_eq!(buffer.snapshot(), vec![b"first".to_vec(), b"second".to_vec(), b"third".to_vec()]);
+
+        assert_eq!(buffer.take(), vec![
+            (Level::Debug, b"first".to_vec()), (Level::Debug, b"second".to_vec()), (Level::Trace, b"third".to_vec()),
+        ]);
+        assert_eq!(take(&buffer), Vec::<String>::new());
+    }
+
+    #[test]
+    fn capacity() {
+        let _clock = freeze();
+
+        let buffer = ErrorContextBuffer::new(Duration::from_secs(10), 2);
+        push(&buffer, &["first", "second", "third"]);
+        assert_eq!(take(&buffer), vec!["second", "third"]);
+
+        let buffer = ErrorContextBuffer::new(Duration::from_secs(10), 0);
+        push(&buffer, &["first"]);
+        assert_eq!(take(&buffer), Vec::<String>::new());
+    }
+
+    #[test]
+    fn window() {
+        let clock = freeze();
+        let buffer = ErrorContextBuffer::new(Duration::from_secs(10), 10);
+
+        push(&buffer, &["old"]);
+        clock.advance(Duration::from_secs(5));
+        push(&buffer, &["recent"]);
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(buffer.snapshot(), vec![b"old".to_vec(), b"recent".to_vec()]);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(buffer.snapshot(), vec![b"recent".to_vec()]);
+        assert_eq!(take(&buffer), vec!["recent"]);
+    }
+}
@@ -0,0 +1,217 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use crate::global;
+use crate::handle::State;
+
+// Only the tail of large log files is included
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const MAX_ROTATED_FILES: usize = 3;
+const MAX_THREAD_FILES: usize = 64;
+
+/// Writes a gzip-compressed tar archive (`.tar.gz`) with the logging configuration (levels, sinks,
+/// static fields), the recent buffered records (see
+/// [`FileSink::error_context()`](crate::FileSink::error_context)) and the log files including the
+/// per-thread ones and the recently rotated ones (`app.log.1` for example), so users can attach it
+/// to bug reports. Only the last 10 MiB of each file are included.
+///
+/// Available with `bundle` feature (enabled by default). Requires the logger to be built via
+/// [`LoggingConfig::build()`](crate::LoggingConfig::build) (see [`Handle::export_bundle()`](crate::Handle::export_bundle)
+/// otherwise).
+pub fn export_bundle<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let state = global::state().ok_or_else(|| io::Error::other("The logger isn't initialized"))?;
+    export(&state, path.as_ref())
+}
+
+pub(crate) fn export(state: &State, path: &Path) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    write_entry(&mut archive, "config.txt", describe(state).as_bytes())?;
+
+    let mut recent = Vec::new();
+    for buffer in state.error_contexts.lock().unwrap().iter() {
+        for data in buffer.snapshot() {
+            recent.extend_from_slice(&data);
+        }
+    }
+    if !recent.is_empty() {
+        write_entry(&mut archive, "recent.log", &recent)?;
+    }
+
+    state.flush();
+
+    for file in state.files.lock().unwrap().iter() {
+        let files = if file.per_thread {
+            get_thread_files(&file.path)
+        } else {
+            vec![file.path.clone()]
+        };
+
+        for path in files.iter().flat_map(|path| std::iter::once(path.clone()).chain(get_rotated_files(path))) {
+            let Some(name) = path.file_name() else {
+                continue;
+            };
+
+            // The file may be removed or be unreadable, which shouldn't break the whole bundle
+            if let Ok(data) = read_tail(&path) {
+                write_entry(&mut archive, &format!("logs/{}", name.to_string_lossy()), &data)?;
+            }
+        }
+    }
+
+    let file = archive.into_inner()?.finish()?;
+    file.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()
+}
+
+fn describe(state: &State) -> String {
+    let mut description = state.levels.describe();
+
+    let files = state.files.lock().unwrap();
+    let sinks: Vec<String> = std::iter::once("console".to_owned())
+        .chain(files.iter().map(|file| if file.per_thread {
+            format!("per-thread file {:?}", file.path)
+        } else {
+            format!("file {:?}", file.path)
+        }))
+        .collect();
+    description.push_str(&format!("sinks: {}\n", sinks.join(", ")));
+
+    let static_fields: Vec<String> = state.static_fields.lock().unwrap().iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    description.push_str(&format!("static fields: {}\n", static_fields.join(" ")));
+
+    description.push_str(&format!("records: {}\n", state.stats));
+    description
+}
+
+// Returns the most recent files rotated by an external tool: `app.log.1`, `app.log.2.gz`, etc.
+fn get_rotated_files(path: &Path) -> Vec<PathBuf> {
+    let Some(name) = path.file_name() else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", name.to_string_lossy());
+
+    get_sibling_files(path, MAX_ROTATED_FILES, |name| name.starts_with(&prefix))
+}
+
+// Returns the most recent per-thread shards of the file: `app.main-1.log`, `app.worker-2.log`, etc.
+fn get_thread_files(path: &Path) -> Vec<PathBuf> {
+    let Some(stem) = path.file_stem() else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", stem.to_string_lossy());
+    let suffix = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+
+    get_sibling_files(path, MAX_THREAD_FILES, |name| {
+        name.len() > prefix.len() + suffix.len() && name.starts_with(&prefix) && name.ends_with(&suffix)
+    })
+}
+
+// Returns the most recently modified files from the directory of the specified file
+fn get_sibling_files<F: Fn(&str) -> bool>(path: &Path, limit: usize, filter: F) -> Vec<PathBuf> {
+    let Some(directory) = path.parent() else {
+        return Vec::new();
+    };
+    let directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<(SystemTime, PathBuf)> = entries.filter_map(|entry| {
+        let entry = entry.ok()?;
+        if !filter(&entry.file_name().to_string_lossy()) {
+            return None;
+        }
+
+        let metadata = entry.metadata().ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+
+        Some((metadata.modified().ok()?, entry.path()))
+    }).collect();
+
+    files.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    files.into_iter().take(limit).map(|(_, path)| path).collect()
+}
+
+fn read_tail(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+
+    let size = file.metadata()?.len();
+    if size > MAX_FILE_SIZE {
+        file.seek(SeekFrom::Start(size - MAX_FILE_SIZE))?;
+    }
+
+    let mut data = Vec::new();
+    file.take(MAX_FILE_SIZE).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn write_entry<W: Write>(archive: &mut tar::Builder<W>, name: &str, data: &[u8]) -> io::Result<()> {
+    let mtime = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |time| time.as_secs());
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+
+    archive.append_data(&mut header, name, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::read::GzDecoder;
+
+    use crate::handle::LogFile;
+
+    use super::*;
+
+    #[test]
+    fn bundle() {
+        let directory = std::env::temp_dir().join(format!("easy-logging-bundle-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir(&directory).unwrap();
+
+        let log_path = directory.join("app.log");
+        fs::write(&log_path, "record\n").unwrap();
+        fs::write(directory.join("app.log.1"), "rotated record\n").unwrap();
+
+        let state = State::new("test".into(), log::Level::Info);
+        state.files.lock().unwrap().push(LogFile {path: log_path, per_thread: false});
+
+        let bundle_path = directory.join("bundle.tar.gz");
+        export(&state, &bundle_path).unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&bundle_path).unwrap()));
+        let mut entries: Vec<(String, String)> = archive.entries().unwrap().map(|entry| {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut data = String::new();
+            entry.read_to_string(&mut data).unwrap();
+            (name, data)
+        }).collect();
+        entries.sort();
+
+        let _ = fs::remove_dir_all(&directory);
+
+        assert_eq!(entries.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec![
+            "config.txt", "logs/app.log", "logs/app.log.1",
+        ]);
+        assert!(entries[0].1.starts_with("module: test\nlevel: INFO\n"), "{}", entries[0].1);
+        assert_eq!(entries[1].1, "record\n");
+        assert_eq!(entries[2].1, "rotated record\n");
+    }
+}
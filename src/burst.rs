@@ -0,0 +1,126 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub(crate) const WINDOW: Duration = Duration::from_secs(1);
+
+pub(crate) struct BurstLimit {
+    pub target_prefix: String,
+    pub max_per_second: u32,
+    window: Mutex<Window>,
+}
+
+struct Window {
+    // Monotonic time of the window start
+    start: Duration,
+    records: u32,
+    dropped: u64,
+}
+
+impl BurstLimit {
+    pub fn new(target_prefix: &str, max_per_second: u32) -> BurstLimit {
+        BurstLimit {
+            target_prefix: target_prefix.to_owned(),
+            max_per_second,
+            // The clock may be replaced until the logger is built, so the first check starts a
+            // new window
+            window: Mutex::new(Window {
+                start: Duration::ZERO,
+                records: 0,
+                dropped: 0,
+            }),
+        }
+    }
+
+    // Starts a new window if the current one has expired, returning the number of records dropped
+    // within the previous one
+    pub fn rotate(&self, now: Duration) -> Option<u64> {
+        let mut window = self.window.lock().unwrap();
+        if now.saturating_sub(window.start) < WINDOW {
+            return None;
+        }
+
+        let dropped = window.dropped;
+        *window = Window {start: now, records: 0, dropped: 0};
+
+        if dropped == 0 {
+            None
+        } else {
+            Some(dropped)
+        }
+    }
+
+    // Returns the number of records dropped so far within the current window (if any)
+    pub fn take_dropped(&self) -> Option<u64> {
+        let mut window = self.window.lock().unwrap();
+        match std::mem::take(&mut window.dropped) {
+            0 => None,
+            dropped => Some(dropped),
+        }
+    }
+
+    // Checks whether records are going to be dropped until the end of the current window
+    pub fn is_exhausted(&self, now: Duration) -> bool {
+        let window = self.window.lock().unwrap();
+        now.saturating_sub(window.start) < WINDOW && window.records >= self.max_per_second
+    }
+
+    pub fn allow(&self) -> bool {
+        let mut window = self.window.lock().unwrap();
+
+        if window.records < self.max_per_second {
+            window.records += 1;
+            true
+        } else {
+            window.dropped += 1;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::{self, tests::freeze};
+
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let clock = freeze();
+        let limit = BurstLimit::new("noisy", 2);
+
+        assert_eq!(limit.rotate(clock::monotonic()), None);
+        assert!(limit.allow());
+        assert!(limit.allow());
+        assert!(limit.is_exhausted(clock::monotonic()));
+        assert!(!limit.allow());
+        assert!(!limit.allow());
+
+        clock.advance(WINDOW / 2);
+        assert_eq!(limit.rotate(clock::monotonic()), None);
+        assert!(limit.is_exhausted(clock::monotonic()));
+
+        clock.advance(WINDOW / 2);
+        assert!(!limit.is_exhausted(clock::monotonic()));
+        assert_eq!(limit.rotate(clock::monotonic()), Some(2));
+        assert!(limit.allow());
+
+        clock.advance(WINDOW);
+        assert_eq!(limit.rotate(clock::monotonic()), None);
+    }
+
+    #[test]
+    fn take_dropped() {
+        let clock = freeze();
+        let limit = BurstLimit::new("noisy", 1);
+
+        limit.rotate(clock::monotonic());
+        assert!(limit.allow());
+        assert!(!limit.allow());
+
+        assert_eq!(limit.take_dropped(), Some(1));
+        assert_eq!(limit.take_dropped(), None);
+
+        clock.advance(WINDOW);
+        assert_eq!(limit.rotate(clock::monotonic()), None);
+    }
+}
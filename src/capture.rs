@@ -0,0 +1,304 @@
+use std::fmt::{self, Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+use log::{Level, Record};
+use log::kv::{self, Key, Value, VisitSource};
+
+use crate::handle::is_target_of;
+
+/// Collects all emitted records in memory to make assertions on them in tests.
+///
+/// Attached to the logger via [`LoggingConfig::capture()`](crate::LoggingConfig::capture).
+#[derive(Clone, Default)]
+pub struct Capture {
+    records: Arc<Mutex<Vec<CapturedRecord>>>,
+}
+
+/// A record collected by [`Capture`].
+#[derive(Clone, Debug)]
+pub struct CapturedRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// Structured key-value pairs of the record
+    pub fields: Vec<(String, String)>,
+}
+
+impl Capture {
+    pub fn new() -> Capture {
+        Capture::default()
+    }
+
+    pub fn records(&self) -> Vec<CapturedRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+
+    /// Starts a query for the records of the specified level.
+    pub fn find(&self, level: Level) -> Query<'_> {
+        Query {
+            capture: self,
+            level,
+            target: None,
+            message: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&self, record: &Record) {
+        let mut fields = FieldCollector(Vec::new());
+        let _ = record.key_values().visit(&mut fields);
+
+        self.records.lock().unwrap().push(CapturedRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            fields: fields.0,
+        });
+    }
+}
+
+struct FieldCollector(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+/// A query over the captured records created by [`Capture::find()`].
+pub struct Query<'a> {
+    capture: &'a Capture,
+    level: Level,
+    target: Option<String>,
+    message: Option<String>,
+    fields: Vec<(String, String)>,
+}
+
+impl Query<'_> {
+    /// Matches records of the specified target and its children.
+    pub fn with_target(mut self, target: &str) -> Self {
+        self.target = Some(target.to_owned());
+        self
+    }
+
+    /// Matches records which message contains the specified string.
+    pub fn with_message(mut self, message: &str) -> Self {
+        self.message = Some(message.to_owned());
+        self
+    }
+
+    /// Matches records which have the field with the specified value (compared by its string
+    /// representation).
+    pub fn with_field<V: Display>(mut self, name: &str, value: V) -> Self {
+        self.fields.push((name.to_owned(), value.to_string()));
+        self
+    }
+
+    pub fn count(&self) -> usize {
+        let records = self.capture.records.lock().unwrap();
+        records.iter().filter(|record| self.mismatches(record).is_empty()).count()
+    }
+
+    pub fn exists(&self) -> bool {
+        self.count() != 0
+    }
+
+    /// Panics with a list of near-misses if there are no matching records.
+    #[track_caller]
+    pub fn assert_exists(&self) {
+        if !self.exists() {
+            panic!("{}", self.failure("No matching records have been logged"));
+        }
+    }
+
+    /// Panics with a list of near-misses if the number of matching records differs from the
+    /// expected one.
+    #[track_caller]
+    pub fn assert_count(&self, expected: usize) {
+        let count = self.count();
+        if count != expected {
+            panic!("{}", self.failure(&format!(
+                "Expected {expected} matching records, but {count} have been logged")));
+        }
+    }
+
+    fn mismatches(&self, record: &CapturedRecord) -> Vec<Mismatch> {
+        let mut mismatches = Vec::new();
+
+        if record.level != self.level {
+            mismatches.push(Mismatch::new("level", self.level, record.level));
+        }
+
+        if let Some(ref target) = self.target {
+            if !is_target_of(&record.target, target) {
+                mismatches.push(Mismatch::new("target", target, &record.target));
+            }
+        }
+
+        if let Some(ref message) = self.message {
+            if !record.message.contains(message.as_str()) {
+                mismatches.push(Mismatch::new("message", format_args!("*{message}*"), &record.message));
+            }
+        }
+
+        for (name, value) in &self.fields {
+            let actual = record.fields.iter().find(|(field, _)| field == name).map(|(_, value)| value);
+            if actual != Some(value) {
+                mismatches.push(Mismatch::new(name, value, actual.map_or("<missing>", String::as_str)));
+            }
+        }
+
+        mismatches
+    }
+
+    fn failure(&self, error: &str) -> String {
+        const MAX_NEAR_MISSES: usize = 10;
+
+        let records = self.capture.records.lock().unwrap();
+        let mut near_misses: Vec<(&CapturedRecord, Vec<Mismatch>)> = records.iter()
+            .map(|record| (record, self.mismatches(record)))
+            .filter(|(_, mismatches)| !mismatches.is_empty())
+            .collect();
+
+        let mut message = format!("{error}. Query: {self}.");
+        if near_misses.is_empty() {
+            return message;
+        }
+
+        near_misses.sort_by_key(|(_, mismatches)| mismatches.len());
+        message.push_str("\nNear-misses:");
+
+        for (record, mismatches) in near_misses.iter().take(MAX_NEAR_MISSES) {
+            message.push_str(&format!("\n  {} [{}] {:?}", record.level, record.target, record.message));
+            for mismatch in mismatches {
+                message.push_str(&format!("\n    {mismatch}"));
+            }
+        }
+
+        if near_misses.len() > MAX_NEAR_MISSES {
+            message.push_str(&format!("\n  ... and {} more", near_misses.len() - MAX_NEAR_MISSES));
+        }
+
+        message
+    }
+}
+
+impl Display for Query<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "level={}", self.level)?;
+        if let Some(ref target) = self.target {
+            write!(f, " target={target}")?;
+        }
+        if let Some(ref message) = self.message {
+            write!(f, " message=*{message}*")?;
+        }
+        for (name, value) in &self.fields {
+            write!(f, " {name}={value}")?;
+        }
+        Ok(())
+    }
+}
+
+struct Mismatch {
+    name: String,
+    expected: String,
+    actual: String,
+}
+
+impl Mismatch {
+    fn new<E: Display, A: Display>(name: &str, expected: E, actual: A) -> Mismatch {
+        Mismatch {
+            name: name.to_owned(),
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }
+    }
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "- {}: expected {}, got {}", self.name, self.expected, self.actual)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+
+    fn capture() -> Capture {
+        let capture = Capture::new();
+
+        capture.push(&Record::builder()
+            .level(Level::Info).target("app::server").args(format_args!("Request has been processed"))
+            .key_values(&[("status", 200), ("size", 1280)])
+            .build());
+        capture.push(&Record::builder()
+            .level(Level::Info).target("app::server").args(format_args!("Request has been processed"))
+            .key_values(&[("status", 404)])
+            .build());
+        capture.push(&Record::builder()
+            .level(Level::Error).target("app::storage").args(format_args!("Disk is full"))
+            .build());
+
+        capture
+    }
+
+    #[test]
+    fn queries() {
+        let capture = capture();
+
+        assert_eq!(capture.find(Level::Info).count(), 2);
+        assert_eq!(capture.find(Level::Info).with_target("app").count(), 2);
+        assert_eq!(capture.find(Level::Info).with_target("app::server").count(), 2);
+        assert_eq!(capture.find(Level::Info).with_target("app::serv").count(), 0);
+        assert_eq!(capture.find(Level::Info).with_message("processed").count(), 2);
+        assert_eq!(capture.find(Level::Info).with_field("status", 404).count(), 1);
+        assert_eq!(capture.find(Level::Info).with_field("status", 200).with_field("size", 1280).count(), 1);
+        assert_eq!(capture.find(Level::Info).with_field("status", 500).count(), 0);
+        assert_eq!(capture.find(Level::Warn).count(), 0);
+
+        assert!(capture.find(Level::Error).with_target("app::storage").exists());
+        capture.find(Level::Info).with_field("status", 200).assert_exists();
+        capture.find(Level::Info).with_target("app::server").assert_count(2);
+
+        capture.clear();
+        assert!(capture.records().is_empty());
+        capture.find(Level::Info).assert_count(0);
+    }
+
+    #[test]
+    fn near_misses() {
+        let capture = capture();
+
+        let error = panic::catch_unwind(AssertUnwindSafe(|| {
+            capture.find(Level::Info).with_message("processed").with_field("status", 500).assert_exists();
+        })).unwrap_err();
+
+        assert_eq!(error.downcast_ref::<String>().unwrap(), &[
+            "No matching records have been logged. Query: level=INFO message=*processed* status=500.",
+            "Near-misses:",
+            "  INFO [app::server] \"Request has been processed\"",
+            "    - status: expected 500, got 200",
+            "  INFO [app::server] \"Request has been processed\"",
+            "    - status: expected 500, got 404",
+            "  ERROR [app::storage] \"Disk is full\"",
+            "    - level: expected INFO, got ERROR",
+            "    - message: expected *processed*, got Disk is full",
+            "    - status: expected 500, got <missing>",
+        ].join("\n"));
+
+        let error = panic::catch_unwind(AssertUnwindSafe(|| {
+            Capture::new().find(Level::Info).with_target("app").assert_count(1);
+        })).unwrap_err();
+
+        assert_eq!(error.downcast_ref::<String>().unwrap(),
+                   "Expected 1 matching records, but 0 have been logged. Query: level=INFO target=app.");
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+
+/// Source of time for record timestamps and the intervals measured by the logger (span durations,
+/// heartbeats, burst limit windows, progress ETA, etc.), so simulations can drive the logs by their
+/// own clock and tests can freeze it.
+///
+/// File syncing (see [`Durability`](crate::Durability)) always uses the real time since it deals
+/// with the real storage.
+pub trait Clock: Send + Sync {
+    /// Current wall clock time which records are timestamped with.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Monotonic time elapsed since an arbitrary fixed point.
+    fn monotonic(&self) -> Duration;
+}
+
+/// The default clock which uses the system time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        lazy_static! {
+            static ref START_TIME: Instant = Instant::now();
+        }
+        START_TIME.elapsed()
+    }
+}
+
+lazy_static! {
+    static ref CLOCK: RwLock<Arc<dyn Clock>> = RwLock::new(Arc::new(SystemClock));
+}
+
+pub(crate) fn set(clock: Arc<dyn Clock>) {
+    *CLOCK.write().unwrap() = clock;
+}
+
+pub(crate) fn now() -> DateTime<Utc> {
+    #[cfg(test)]
+    if let Some(clock) = tests::thread_clock() {
+        return clock.now();
+    }
+    CLOCK.read().unwrap().now()
+}
+
+pub(crate) fn monotonic() -> Duration {
+    #[cfg(test)]
+    if let Some(clock) = tests::thread_clock() {
+        return clock.monotonic();
+    }
+    CLOCK.read().unwrap().monotonic()
+}
+
+// Returns time elapsed since the specified monotonic time
+pub(crate) fn elapsed(since: Duration) -> Duration {
+    monotonic().saturating_sub(since)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::cell::RefCell;
+    use std::sync::Mutex;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    thread_local! {
+        // Tests are run in parallel, so the clock is frozen only for the current thread
+        static THREAD_CLOCK: RefCell<Option<Arc<FrozenClock>>> = const { RefCell::new(None) };
+    }
+
+    pub struct FrozenClock {
+        elapsed: Mutex<Duration>,
+    }
+
+    impl FrozenClock {
+        pub fn advance(&self, duration: Duration) {
+            *self.elapsed.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for FrozenClock {
+        fn now(&self) -> DateTime<Utc> {
+            Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap() + *self.elapsed.lock().unwrap()
+        }
+
+        fn monotonic(&self) -> Duration {
+            Duration::from_secs(1000) + *self.elapsed.lock().unwrap()
+        }
+    }
+
+    // Freezes the clock for the current thread
+    pub fn freeze() -> Arc<FrozenClock> {
+        let clock = Arc::new(FrozenClock {elapsed: Mutex::new(Duration::ZERO)});
+        THREAD_CLOCK.with(|thread_clock| thread_clock.replace(Some(clock.clone())));
+        clock
+    }
+
+    pub(super) fn thread_clock() -> Option<Arc<FrozenClock>> {
+        THREAD_CLOCK.with(|clock| clock.borrow().clone())
+    }
+}
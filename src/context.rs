@@ -1,10 +1,19 @@
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 
 use lazy_static::lazy_static;
 use log::Level;
 
 lazy_static! {
-    static ref GLOBAL_CONTEXT: Mutex<Option<GlobalContextValue>> = Mutex::new(None);
+    static ref GLOBAL_CONTEXT: Mutex<GlobalContextState> = Mutex::new(None);
+}
+
+static INHERITED_CONTEXT: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+pub(crate) type GlobalContextState = Option<GlobalContextValue>;
+
+pub(crate) fn lock_global_context() -> MutexGuard<'static, GlobalContextState> {
+    GLOBAL_CONTEXT.lock().unwrap_or_else(|err| err.into_inner())
 }
 
 pub struct GlobalContext {
@@ -25,6 +34,7 @@ impl GlobalContext {
             }
             context.replace(GlobalContextValue {
                 min_level,
+                name: name.to_owned(),
                 message
             });
         }
@@ -33,10 +43,53 @@ impl GlobalContext {
     }
 
     pub(crate) fn get(level: Level) -> String {
+        let mut message: String = INHERITED_CONTEXT.read().unwrap().iter()
+            .map(|name| format!("[{name}] "))
+            .collect();
+
+        match GLOBAL_CONTEXT.lock().unwrap().as_ref() {
+            Some(context) if level >= context.min_level => message.push_str(&context.message),
+            _ => {},
+        }
+
+        #[cfg(feature = "tower")]
+        if let Some(request) = crate::request::get() {
+            message.push_str(&format!("[{request}] "));
+        }
+
+        message
+    }
+
+    pub(crate) fn get_name(level: Level) -> Option<String> {
+        let mut names = INHERITED_CONTEXT.read().unwrap().clone();
+
         match GLOBAL_CONTEXT.lock().unwrap().as_ref() {
-            Some(context) if level >= context.min_level => context.message.clone(),
-            _ => String::new(),
+            Some(context) if level >= context.min_level => names.push(context.name.clone()),
+            _ => {},
+        }
+
+        #[cfg(feature = "tower")]
+        names.extend(crate::request::get());
+
+        if names.is_empty() {
+            None
+        } else {
+            Some(names.join(" / "))
+        }
+    }
+
+    // Returns the inherited and the current context names regardless of the level
+    pub(crate) fn get_names() -> Vec<String> {
+        let mut names = INHERITED_CONTEXT.read().unwrap().clone();
+        if let Some(context) = GLOBAL_CONTEXT.lock().unwrap().as_ref() {
+            names.push(context.name.clone());
         }
+        names
+    }
+
+    // Sets the context inherited from the parent process which precedes the current one
+    pub(crate) fn set_inherited(names: Vec<String>) {
+        *INHERITED_CONTEXT.write().unwrap() = names;
     }
 }
 
@@ -46,7 +99,64 @@ impl Drop for GlobalContext {
     }
 }
 
-struct GlobalContextValue {
+pub(crate) struct GlobalContextValue {
     min_level: Level,
+    name: String,
     message: String,
-}
\ No newline at end of file
+}
+
+static PROGRESS_ACTIVE: AtomicBool = AtomicBool::new(false);
+static PROGRESS_CURRENT: AtomicU64 = AtomicU64::new(0);
+// Zero means that progress is measured in percents
+static PROGRESS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// Renders operation progress (`[42%] ` or `[3/17] `) before each log message while it's alive.
+pub struct ProgressContext {
+}
+
+impl ProgressContext {
+    pub fn new_percent() -> ProgressContext {
+        ProgressContext::new(0)
+    }
+
+    pub fn new_steps(total: u64) -> ProgressContext {
+        ProgressContext::new(total)
+    }
+
+    fn new(total: u64) -> ProgressContext {
+        if PROGRESS_ACTIVE.swap(true, Ordering::SeqCst) {
+            panic!("An attempt to set a nested progress context");
+        }
+
+        PROGRESS_CURRENT.store(0, Ordering::Relaxed);
+        PROGRESS_TOTAL.store(total, Ordering::Relaxed);
+
+        ProgressContext{}
+    }
+
+    pub fn set_percent(&self, percent: u8) {
+        PROGRESS_CURRENT.store(percent.min(100).into(), Ordering::Relaxed);
+    }
+
+    pub fn set_step(&self, step: u64) {
+        PROGRESS_CURRENT.store(step, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get() -> Option<String> {
+        if !PROGRESS_ACTIVE.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let current = PROGRESS_CURRENT.load(Ordering::Relaxed);
+        Some(match PROGRESS_TOTAL.load(Ordering::Relaxed) {
+            0 => format!("{current}%"),
+            total => format!("{current}/{total}"),
+        })
+    }
+}
+
+impl Drop for ProgressContext {
+    fn drop(&mut self) {
+        PROGRESS_ACTIVE.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,74 @@
+use std::str::FromStr;
+
+use log::LevelFilter;
+
+// A RUST_LOG-style directive: `target=level` or just `level` which applies to all targets
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Directive {
+    pub target: Option<String>,
+    pub level: LevelFilter,
+}
+
+pub(crate) fn parse(directives: &str) -> Result<Vec<Directive>, String> {
+    let mut parsed = Vec::new();
+
+    for line in directives.lines() {
+        let line = match line.find('#') {
+            Some(index) => &line[..index],
+            None => line,
+        };
+
+        for directive in line.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            parsed.push(parse_directive(directive).ok_or_else(|| format!(
+                "Invalid logging directive: {directive:?}"))?);
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_directive(directive: &str) -> Option<Directive> {
+    let (target, level) = match directive.split_once('=') {
+        Some((target, level)) => {
+            let target = target.trim();
+            if target.is_empty() {
+                return None;
+            }
+            (Some(target.to_owned()), level.trim())
+        },
+        None => (None, directive),
+    };
+
+    Some(Directive {
+        target,
+        level: LevelFilter::from_str(level).ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directive(target: Option<&str>, level: LevelFilter) -> Directive {
+        Directive {target: target.map(ToOwned::to_owned), level}
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse("").unwrap(), []);
+
+        assert_eq!(parse("info, hyper = warn,tokio::net=off # noisy\n\n# comment\ntrace").unwrap(), [
+            directive(None, LevelFilter::Info),
+            directive(Some("hyper"), LevelFilter::Warn),
+            directive(Some("tokio::net"), LevelFilter::Off),
+            directive(None, LevelFilter::Trace),
+        ]);
+    }
+
+    #[test]
+    fn invalid() {
+        for directives in ["verbose", "hyper=", "=debug", "hyper=debug,tokio=loud"] {
+            assert!(parse(directives).is_err(), "{directives:?}");
+        }
+    }
+}
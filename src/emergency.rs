@@ -0,0 +1,49 @@
+const PREFIX: &[u8] = b"E: ";
+const BUF_SIZE: usize = 1024;
+
+/// Writes the message to stderr bypassing the logger, its locks and allocations, so it's safe to
+/// be called from signal handlers.
+///
+/// The message is written as is (prefixed with `E: ` and terminated with a newline) with a single
+/// write(2) call when it fits into the internal buffer.
+pub fn emergency(message: &str) {
+    let message = message.as_bytes();
+    let mut buf = [0u8; BUF_SIZE];
+    let mut size = 0;
+
+    for part in [PREFIX, message, b"\n"] {
+        if size + part.len() <= buf.len() {
+            buf[size..size + part.len()].copy_from_slice(part);
+            size += part.len();
+        } else {
+            write(&buf[..size]);
+            write(part);
+            size = 0;
+        }
+    }
+
+    write(&buf[..size]);
+}
+
+#[cfg(unix)]
+fn write(mut data: &[u8]) {
+    while !data.is_empty() {
+        let result = unsafe {
+            libc::write(libc::STDERR_FILENO, data.as_ptr() as *const libc::c_void, data.len())
+        };
+
+        if result > 0 {
+            data = &data[result as usize..];
+        } else if result < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+            continue;
+        } else {
+            break;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn write(data: &[u8]) {
+    use std::io::Write;
+    let _ = std::io::stderr().write_all(data);
+}
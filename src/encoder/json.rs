@@ -0,0 +1,137 @@
+use std::io::{self, Write};
+
+use log::Record;
+
+use crate::context::{GlobalContext, ProgressContext};
+
+use super::{EncodeContext, Encoder, SourceLocation, Timestamp, TimestampFormat, static_field_name};
+
+/// Encodes records as JSON objects, one per line.
+#[derive(Clone, Default)]
+pub struct JsonEncoder {
+    timestamp_format: TimestampFormat,
+}
+
+impl JsonEncoder {
+    pub fn new() -> JsonEncoder {
+        JsonEncoder::default()
+    }
+
+    /// Writes numeric epoch timestamps (`ts_ms` or `ts_ns` field) instead of RFC 3339 strings,
+    /// which is cheaper to produce and to ingest.
+    pub fn timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.timestamp_format = format;
+        self
+    }
+}
+
+impl Encoder for JsonEncoder {
+    fn encode(&self, record: &Record, context: &EncodeContext<'_>, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.timestamp_format.format(context.time) {
+            Timestamp::String(time) => {
+                write!(buf, r#"{{"time":"#)?;
+                write_string(buf, &time)?;
+            },
+            Timestamp::Number(name, time) => write!(buf, r#"{{"{name}":{time}"#)?,
+        }
+
+        write!(buf, r#","level":"#)?;
+        write_string(buf, record.level().as_str())?;
+
+        write!(buf, r#","target":"#)?;
+        write_string(buf, record.target())?;
+
+        for (name, value) in context.static_fields {
+            write!(buf, ",")?;
+            write_string(buf, &static_field_name(name))?;
+            write!(buf, ":")?;
+            write_string(buf, value)?;
+        }
+
+        if context.source_location == SourceLocation::File {
+            if let Some(file) = record.file() {
+                write!(buf, r#","file":"#)?;
+                write_string(buf, file)?;
+            }
+
+            if let Some(line) = record.line() {
+                write!(buf, r#","line":{line}"#)?;
+            }
+        }
+
+        if let Some(name) = GlobalContext::get_name(context.max_level) {
+            write!(buf, r#","context":"#)?;
+            write_string(buf, &name)?;
+        }
+
+        if let Some(progress) = ProgressContext::get() {
+            write!(buf, r#","progress":"#)?;
+            write_string(buf, &progress)?;
+        }
+
+        write!(buf, r#","message":"#)?;
+        write_string(buf, &record.args().to_string())?;
+
+        writeln!(buf, "}}")
+    }
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) -> io::Result<()> {
+    buf.push(b'"');
+
+    for c in value.chars() {
+        match c {
+            '"' => buf.extend_from_slice(br#"\""#),
+            '\\' => buf.extend_from_slice(br"\\"),
+            '\n' => buf.extend_from_slice(br"\n"),
+            '\r' => buf.extend_from_slice(br"\r"),
+            '\t' => buf.extend_from_slice(br"\t"),
+            c if c.is_control() => write!(buf, r"\u{:04x}", c as u32)?,
+            c => write!(buf, "{c}")?,
+        }
+    }
+
+    buf.push(b'"');
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use crate::encoder::tests::encode;
+
+    use super::*;
+
+    fn string(value: &str) -> String {
+        let mut buf = Vec::new();
+        write_string(&mut buf, value).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(string(""), r#""""#);
+        assert_eq!(string(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+        assert_eq!(string("a\nb\r\tc"), r#""a\nb\r\tc""#);
+        assert_eq!(string("\0\u{1b}[1m\u{7f}"), r#""\u0000\u001b[1m\u007f""#);
+        assert_eq!(string("юникод \u{fffd}"), "\"юникод \u{fffd}\"");
+    }
+
+    #[test]
+    fn record() {
+        let fields = [("host".to_owned(), "a\"b".to_owned()), ("message".to_owned(), "static".to_owned())];
+        let record = Record::builder()
+            .level(Level::Warn)
+            .target("app::db")
+            .file(Some("src/db.rs"))
+            .line(Some(12))
+            .args(format_args!("line\nbreak"))
+            .build();
+
+        assert_eq!(encode(&JsonEncoder::new(), &record, &fields), concat!(
+            r#"{"time":"2024-01-02T03:04:05.000+00:00","level":"WARN","target":"app::db","#,
+            r#""host":"a\"b","_message":"static","file":"src/db.rs","line":12,"message":"line\nbreak"}"#, "\n",
+        ));
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::Level;
+
+use super::TargetColumn;
+
+// Width of `file:line` column when terminal width is unknown
+const DEFAULT_LOCATION_WIDTH: usize = 14;
+const MAX_LOCATION_WIDTH: usize = 30;
+const MIN_LINE_WIDTH: usize = 3;
+
+const DEFAULT_TARGET_WIDTH: usize = 10;
+const MAX_TARGET_WIDTH: usize = 40;
+
+// Widths of the columns which prefix the message in the text encoder. They are derived from the
+// terminal width, so wide terminals get less truncated file names and targets.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub level: usize,
+    pub location: usize,
+    // Targets aren't padded if the width is unknown to not waste the space of non-terminal outputs
+    pub target: Option<usize>,
+}
+
+impl Layout {
+    // Level names are aligned only if `aligned_level_names` are specified
+    pub fn new(
+        terminal_width: Option<usize>, aligned_level_names: Option<fn (Level) -> &'static str>,
+        target: Option<&TargetColumn>,
+    ) -> Layout {
+        let level = aligned_level_names.map_or(0, |get_level_name| {
+            Level::iter().map(|level| get_level_name(level).chars().count()).max().unwrap_or(0)
+        });
+
+        let column_width = |default: usize, max: usize| match terminal_width {
+            Some(width) => (width / 8).clamp(default, max),
+            None => default,
+        };
+
+        let target = target.and_then(|column| match column.get_max_width() {
+            Some(width) => Some(width),
+            None => terminal_width.map(|_| column_width(DEFAULT_TARGET_WIDTH, MAX_TARGET_WIDTH)),
+        });
+
+        Layout {
+            level,
+            location: column_width(DEFAULT_LOCATION_WIDTH, MAX_LOCATION_WIDTH),
+            target,
+        }
+    }
+
+    pub fn format_location(&self, file: &str, line: u32) -> String {
+        let file = file.strip_prefix("src/").unwrap_or(file);
+
+        // Long line numbers take the space of the file name
+        let line_width = line.to_string().len().max(MIN_LINE_WIDTH);
+        let file_width = self.location.saturating_sub(line_width + 1);
+
+        let length = file.chars().count();
+        let file: String = file.chars().skip(length.saturating_sub(file_width)).collect();
+
+        format!("{file:>file_width$}:{line:0line_width$}")
+    }
+}
+
+// Caches the layout until the terminal width changes. It's accessed by all sinks on each record, so
+// it doesn't take any locks.
+#[derive(Default)]
+pub(crate) struct LayoutCache {
+    // Layout of the outputs which aren't terminals
+    fixed: OnceLock<Layout>,
+    // Terminal width and its layout packed into 16-bit fields (0 if not cached yet)
+    terminal: AtomicU64,
+}
+
+impl LayoutCache {
+    pub fn get<F: FnOnce() -> Layout>(&self, terminal_width: Option<usize>, calculate: F) -> Layout {
+        let Some(width) = terminal_width else {
+            return *self.fixed.get_or_init(calculate);
+        };
+
+        if let Some(layout) = unpack(self.terminal.load(Ordering::Relaxed), width) {
+            return layout;
+        }
+
+        let layout = calculate();
+        if let Some(packed) = pack(width, layout) {
+            self.terminal.store(packed, Ordering::Relaxed);
+        }
+        layout
+    }
+}
+
+// Returns None if some of the values don't fit (the layout isn't cached then)
+fn pack(width: usize, layout: Layout) -> Option<u64> {
+    let target = match layout.target {
+        Some(target) => target.checked_add(1)?,
+        None => 0,
+    };
+
+    [width, layout.level, layout.location, target].into_iter().rev().try_fold(0, |packed, value| {
+        Some(packed << 16 | u64::from(u16::try_from(value).ok()?))
+    })
+}
+
+fn unpack(packed: u64, width: usize) -> Option<Layout> {
+    let field = |index: u32| (packed >> (16 * index) & 0xFFFF) as usize;
+    if packed == 0 || field(0) != width {
+        return None;
+    }
+
+    Some(Layout {
+        level: field(1),
+        location: field(2),
+        target: field(3).checked_sub(1),
+    })
+}
+
+// The encoder may be reconfigured after cloning, so the cache isn't shared
+impl Clone for LayoutCache {
+    fn clone(&self) -> Self {
+        LayoutCache::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packing() {
+        for layout in [
+            Layout {level: 1, location: 14, target: None},
+            Layout {level: 5, location: 30, target: Some(0)},
+            Layout {level: 0, location: 0, target: Some(65534)},
+        ] {
+            let packed = pack(120, layout).unwrap();
+            assert!(unpack(packed, 120) == Some(layout));
+            assert!(unpack(packed, 80).is_none());
+        }
+
+        assert!(pack(120, Layout {level: 1, location: 14, target: Some(65535)}).is_none());
+        assert!(unpack(0, 0).is_none());
+    }
+}
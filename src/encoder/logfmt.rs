@@ -0,0 +1,132 @@
+use std::io::{self, Write};
+
+use log::Record;
+
+use crate::context::{GlobalContext, ProgressContext};
+
+use super::{EncodeContext, Encoder, SourceLocation, Timestamp, TimestampFormat, static_field_name};
+
+/// Encodes records in logfmt format (`key=value` pairs separated by spaces), one per line.
+#[derive(Clone, Default)]
+pub struct LogfmtEncoder {
+    timestamp_format: TimestampFormat,
+}
+
+impl LogfmtEncoder {
+    pub fn new() -> LogfmtEncoder {
+        LogfmtEncoder::default()
+    }
+
+    /// Writes numeric epoch timestamps (`ts_ms` or `ts_ns` field) instead of RFC 3339 strings,
+    /// which is cheaper to produce and to ingest.
+    pub fn timestamp_format(mut self, format: TimestampFormat) -> Self {
+        self.timestamp_format = format;
+        self
+    }
+}
+
+impl Encoder for LogfmtEncoder {
+    fn encode(&self, record: &Record, context: &EncodeContext<'_>, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self.timestamp_format.format(context.time) {
+            Timestamp::String(time) => write!(buf, "time={time}")?,
+            Timestamp::Number(name, time) => write!(buf, "{name}={time}")?,
+        }
+        write!(buf, " level={}", record.level().as_str().to_lowercase())?;
+
+        write!(buf, " target=")?;
+        write_value(buf, record.target())?;
+
+        for (name, value) in context.static_fields {
+            write!(buf, " {}=", static_field_name(name))?;
+            write_value(buf, value)?;
+        }
+
+        let location = (record.file(), record.line());
+        if let (SourceLocation::File, (Some(file), Some(line))) = (context.source_location, location) {
+            write!(buf, " location=")?;
+            write_value(buf, &format!("{file}:{line}"))?;
+        }
+
+        if let Some(name) = GlobalContext::get_name(context.max_level) {
+            write!(buf, " context=")?;
+            write_value(buf, &name)?;
+        }
+
+        if let Some(progress) = ProgressContext::get() {
+            write!(buf, " progress=")?;
+            write_value(buf, &progress)?;
+        }
+
+        write!(buf, " msg=")?;
+        write_value(buf, &record.args().to_string())?;
+
+        writeln!(buf)
+    }
+}
+
+pub(crate) fn write_value(buf: &mut Vec<u8>, value: &str) -> io::Result<()> {
+    let needs_quoting = value.is_empty() || value.chars().any(|c| {
+        c == ' ' || c == '=' || c == '"' || c.is_control()
+    });
+
+    if !needs_quoting {
+        return write!(buf, "{value}");
+    }
+
+    write!(buf, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(buf, r#"\""#)?,
+            '\\' => write!(buf, r"\\")?,
+            '\n' => write!(buf, r"\n")?,
+            c if c.is_control() => write!(buf, r"\u{:04x}", c as u32)?,
+            c => write!(buf, "{c}")?,
+        }
+    }
+    write!(buf, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use crate::encoder::tests::encode;
+
+    use super::*;
+
+    fn value(value: &str) -> String {
+        let mut buf = Vec::new();
+        write_value(&mut buf, value).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(value("plain"), "plain");
+        assert_eq!(value(""), r#""""#);
+        assert_eq!(value("a b"), r#""a b""#);
+        assert_eq!(value("a=b"), r#""a=b""#);
+        assert_eq!(value(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+        assert_eq!(value("a\nb"), r#""a\nb""#);
+        assert_eq!(value("\0\u{1b}"), r#""\u0000\u001b""#);
+        assert_eq!(value(r"back\slash"), r"back\slash");
+        assert_eq!(value("юникод\u{fffd}"), "юникод\u{fffd}");
+    }
+
+    #[test]
+    fn record() {
+        let fields = [("host".to_owned(), "a b".to_owned()), ("level".to_owned(), "static".to_owned())];
+        let record = Record::builder()
+            .level(Level::Info)
+            .target("app")
+            .file(Some("src/main.rs"))
+            .line(Some(7))
+            .args(format_args!("started"))
+            .build();
+
+        assert_eq!(encode(&LogfmtEncoder::new(), &record, &fields), concat!(
+            r#"time=2024-01-02T03:04:05.000+00:00 level=info target=app host="a b" _level=static "#,
+            "location=src/main.rs:7 msg=started\n",
+        ));
+    }
+}
@@ -0,0 +1,178 @@
+mod json;
+mod layout;
+mod logfmt;
+mod target;
+mod text;
+
+use std::borrow::Cow;
+use std::io;
+
+use log::{Level, Record};
+
+pub use self::json::JsonEncoder;
+pub use self::logfmt::LogfmtEncoder;
+pub(crate) use self::logfmt::write_value as write_logfmt_value;
+pub use self::target::TargetColumn;
+pub use self::text::TextEncoder;
+pub(crate) use self::text::RecordData;
+
+/// Encoder defines the layout of log records independently of the transport they are written to.
+pub trait Encoder: Send + Sync {
+    /// Encodes the record into the buffer. Line-oriented encoders are expected to terminate the
+    /// record with a newline.
+    fn encode(&self, record: &Record, context: &EncodeContext, buf: &mut Vec<u8>) -> io::Result<()>;
+}
+
+/// Sink-specific information available to the encoder.
+#[non_exhaustive]
+pub struct EncodeContext<'a> {
+    /// Whether the transport supports colored output for this record
+    pub colored: bool,
+    /// Logging level the logger has been configured with
+    pub max_level: Level,
+    /// Fields which should be attached to every record
+    pub static_fields: &'a [(String, String)],
+    /// How source location of the record should be rendered
+    pub source_location: SourceLocation,
+    /// Time zone of the timestamps
+    pub time_zone: TimeZone,
+    /// Time of the record (see [`Clock`](crate::Clock)) in the sink time zone
+    pub time: chrono::DateTime<chrono::FixedOffset>,
+    /// Width of the terminal the record is written to
+    pub terminal_width: Option<usize>,
+}
+
+/// Defines how source locations of records are rendered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceLocation {
+    /// File and line. The default one unless `no-source-location` feature is enabled, which
+    /// turns it into `Omit`.
+    File,
+    /// Record target which is stable and doesn't reveal source tree layout to end users
+    Target,
+    /// No source location
+    Omit,
+}
+
+impl SourceLocation {
+    pub(crate) fn resolve(self) -> SourceLocation {
+        match self {
+            SourceLocation::File if cfg!(feature = "no-source-location") => SourceLocation::Omit,
+            location => location,
+        }
+    }
+}
+
+impl Default for SourceLocation {
+    fn default() -> Self {
+        SourceLocation::File.resolve()
+    }
+}
+
+pub(crate) fn get_default_level_name(level: Level) -> &'static str {
+    match level {
+        Level::Error => "E: ",
+        Level::Warn  => "W: ",
+        Level::Info  => "I: ",
+        Level::Debug => "D: ",
+        Level::Trace => "T: ",
+    }
+}
+
+/// Time zone of record timestamps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeZone {
+    #[default]
+    Local,
+    Utc,
+}
+
+impl TimeZone {
+    pub(crate) fn convert(self, time: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::FixedOffset> {
+        match self {
+            TimeZone::Local => time.with_timezone(&chrono::Local).fixed_offset(),
+            TimeZone::Utc => time.fixed_offset(),
+        }
+    }
+}
+
+/// Format of record timestamps in machine-oriented encoders.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 string in `time` field
+    #[default]
+    Rfc3339,
+    /// Milliseconds since Unix epoch in `ts_ms` field
+    EpochMillis,
+    /// Nanoseconds since Unix epoch in `ts_ns` field
+    EpochNanos,
+}
+
+// Epoch timestamps don't depend on the time zone
+enum Timestamp {
+    String(String),
+    Number(&'static str, i64),
+}
+
+impl TimestampFormat {
+    fn format(self, time: chrono::DateTime<chrono::FixedOffset>) -> Timestamp {
+        match self {
+            TimestampFormat::Rfc3339 => Timestamp::String(format_timestamp(time)),
+            TimestampFormat::EpochMillis => Timestamp::Number("ts_ms", time.timestamp_millis()),
+            TimestampFormat::EpochNanos => Timestamp::Number(
+                "ts_ns", time.timestamp_nanos_opt().unwrap_or(i64::MAX)),
+        }
+    }
+}
+
+// Names of the fields written by the structured encoders themselves
+const RESERVED_FIELDS: &[&str] = &[
+    "time", "ts_ms", "ts_ns", "level", "target", "file", "line", "location", "context", "progress",
+    "message", "msg",
+];
+
+// Prefixes the names of static fields which clash with the built-in ones to not produce duplicate
+// keys
+fn static_field_name(name: &str) -> Cow<'_, str> {
+    if RESERVED_FIELDS.contains(&name) {
+        Cow::Owned(format!("_{name}"))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+fn format_timestamp(time: chrono::DateTime<chrono::FixedOffset>) -> String {
+    time.to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use chrono::TimeZone as _;
+    use log::Record;
+
+    use super::*;
+
+    // Encodes the record with deterministic time and settings
+    pub fn encode<E: Encoder>(encoder: &E, record: &Record, static_fields: &[(String, String)]) -> String {
+        let context = EncodeContext {
+            colored: false,
+            max_level: Level::Trace,
+            static_fields,
+            source_location: SourceLocation::File,
+            time_zone: TimeZone::Utc,
+            time: chrono::Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap().fixed_offset(),
+            terminal_width: None,
+        };
+
+        let mut buf = Vec::new();
+        encoder.encode(record, &context, &mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn reserved_fields() {
+        assert_eq!(static_field_name("host"), "host");
+        assert_eq!(static_field_name("level"), "_level");
+        assert_eq!(static_field_name("message"), "_message");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::handle::is_target_of;
+
+/// Defines how record targets are rendered in the target column of the text encoder.
+#[derive(Clone, Default)]
+pub struct TargetColumn {
+    abbreviate: bool,
+    aliases: Vec<(String, String)>,
+    max_width: Option<usize>,
+}
+
+impl TargetColumn {
+    pub fn new() -> TargetColumn {
+        TargetColumn::default()
+    }
+
+    /// Keeps only the first and the last segment of deep targets (`app::…::backend`).
+    pub fn abbreviate(mut self) -> Self {
+        self.abbreviate = true;
+        self
+    }
+
+    /// Replaces the specified target prefix with the alias. The most specific target wins.
+    pub fn alias(mut self, target: &str, alias: &str) -> Self {
+        self.aliases.push((target.to_owned(), alias.to_owned()));
+        self
+    }
+
+    /// Pads the column to the specified width truncating longer targets from the left. By default
+    /// the width is derived from the terminal width and targets aren't padded for other outputs.
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    pub(crate) fn get_max_width(&self) -> Option<usize> {
+        self.max_width
+    }
+
+    // Formats the target padding it to the column width
+    pub(crate) fn format(&self, target: &str, width: Option<usize>) -> String {
+        let mut target = target.to_owned();
+
+        let alias = self.aliases.iter()
+            .filter(|(prefix, _)| is_target_of(&target, prefix))
+            .max_by_key(|(prefix, _)| prefix.len());
+
+        if let Some((prefix, alias)) = alias {
+            target = format!("{alias}{}", &target[prefix.len()..]);
+        }
+
+        if self.abbreviate {
+            let segments: Vec<&str> = target.split("::").collect();
+            if segments.len() > 2 {
+                target = format!("{}::…::{}", segments[0], segments[segments.len() - 1]);
+            }
+        }
+
+        if let Some(width) = width {
+            let length = target.chars().count();
+            if length > width {
+                let skip = length - width.saturating_sub(1);
+                target = if width == 0 {
+                    String::new()
+                } else {
+                    format!("…{}", target.chars().skip(skip).collect::<String>())
+                };
+            }
+            target = format!("{target:<width$}");
+        }
+
+        target
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abbreviation() {
+        let column = TargetColumn::new().abbreviate();
+
+        assert_eq!(column.format("app", None), "app");
+        assert_eq!(column.format("app::server", None), "app::server");
+        assert_eq!(column.format("app::server::http::backend", None), "app::…::backend");
+    }
+
+    #[test]
+    fn aliases() {
+        let column = TargetColumn::new()
+            .alias("app", "@")
+            .alias("app::server::http", "http")
+            .abbreviate();
+
+        assert_eq!(column.format("app", None), "@");
+        assert_eq!(column.format("application", None), "application");
+        assert_eq!(column.format("app::server", None), "@::server");
+        assert_eq!(column.format("app::server::http::client::pool", None), "http::…::pool");
+        assert_eq!(column.format("app::storage::disk::io", None), "@::…::io");
+    }
+
+    #[test]
+    fn width() {
+        let column = TargetColumn::new();
+
+        assert_eq!(column.format("app", Some(6)), "app   ");
+        assert_eq!(column.format("app::db", Some(7)), "app::db");
+        assert_eq!(column.format("app::db", Some(6)), "…p::db");
+        assert_eq!(column.format("сервер", Some(3)), "…ер");
+        assert_eq!(column.format("app", Some(1)), "…");
+        assert_eq!(column.format("app", Some(0)), "");
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use ansi_term::{Color, Style};
+use chrono::{DateTime, FixedOffset};
+use log::{Level, Record};
+
+use crate::context::{GlobalContext, ProgressContext};
+use crate::highlight::Highlighted;
+use crate::style;
+
+use super::{EncodeContext, Encoder, SourceLocation, TargetColumn};
+use super::layout::{Layout, LayoutCache};
+
+/// Human-readable encoder used by console and file sinks by default.
+#[derive(Clone)]
+pub struct TextEncoder {
+    detailed: bool,
+    full_timestamps: bool,
+    get_level_name: fn (level: Level) -> &'static str,
+    align_level_names: bool,
+    highlight_values: bool,
+    indent_multiline: bool,
+    static_fields: bool,
+    target: Option<TargetColumn>,
+    metadata_style: Option<Style>,
+    layout: LayoutCache,
+}
+
+impl TextEncoder {
+    /// Creates an encoder which prefixes messages with time and source location.
+    pub fn new() -> TextEncoder {
+        TextEncoder {
+            detailed: true,
+            full_timestamps: false,
+            get_level_name: super::get_default_level_name,
+            align_level_names: false,
+            highlight_values: false,
+            indent_multiline: false,
+            static_fields: false,
+            target: None,
+            metadata_style: None,
+            layout: LayoutCache::default(),
+        }
+    }
+
+    /// Outputs only level name, context and the message.
+    pub fn compact(mut self) -> Self {
+        self.detailed = false;
+        self
+    }
+
+    /// Prefixes messages with full RFC 3339 timestamps including date and UTC offset instead of
+    /// only time of day.
+    pub fn full_timestamps(mut self) -> Self {
+        self.full_timestamps = true;
+        self
+    }
+
+    pub fn level_names(mut self, get: fn (level: Level) -> &'static str) -> Self {
+        self.get_level_name = get;
+        self
+    }
+
+    /// Pads level names to the widest one, so messages are aligned when the names have different
+    /// widths (localized ones for example).
+    pub fn align_level_names(mut self) -> Self {
+        self.align_level_names = true;
+        self
+    }
+
+    /// Highlights numbers, durations, byte sizes and quoted strings in messages when output is
+    /// colored.
+    pub fn highlight_values(mut self) -> Self {
+        self.highlight_values = true;
+        self
+    }
+
+    /// Indents continuation lines of multi-line messages to the message start, so they are
+    /// visually attached to their record.
+    pub fn indent_multiline(mut self) -> Self {
+        self.indent_multiline = true;
+        self
+    }
+
+    /// Prefixes messages with the static fields (`[service=billing env=prod] `).
+    pub fn static_fields(mut self) -> Self {
+        self.static_fields = true;
+        self
+    }
+
+    /// Renders the metadata columns (time, location and target) with the specified style instead
+    /// of the level color when output is colored, for example `Color::Fixed(244).normal()`.
+    pub fn metadata_style(mut self, style: Style) -> Self {
+        self.metadata_style = Some(style);
+        self
+    }
+
+    /// Adds the record target column before the level name.
+    pub fn target(mut self, column: TargetColumn) -> Self {
+        self.target = Some(column);
+        self
+    }
+}
+
+impl Default for TextEncoder {
+    fn default() -> Self {
+        TextEncoder::new()
+    }
+}
+
+impl Encoder for TextEncoder {
+    fn encode(&self, record: &Record, context: &EncodeContext<'_>, buf: &mut Vec<u8>) -> io::Result<()> {
+        let mut global_context = GlobalContext::get(context.max_level);
+        if let Some(progress) = ProgressContext::get() {
+            global_context = format!("{global_context}[{progress}] ");
+        }
+
+        self.render(&RecordData {
+            time: context.time,
+            level: record.level(),
+            target: record.target(),
+            file: record.file(),
+            line: record.line(),
+            context: global_context,
+            style: style::get_style(),
+            message: record.args(),
+        }, context, buf)
+    }
+}
+
+// Record data the text encoder renders. The live logger takes time, context and style from the
+// environment while `format_record()` gets them from the caller.
+pub(crate) struct RecordData<'a, T: Display> {
+    pub time: DateTime<FixedOffset>,
+    pub level: Level,
+    pub target: &'a str,
+    pub file: Option<&'a str>,
+    pub line: Option<u32>,
+    pub context: String,
+    pub style: Option<Style>,
+    pub message: &'a T,
+}
+
+impl TextEncoder {
+    pub(crate) fn render<T: Display>(&self, record: &RecordData<T>, context: &EncodeContext<'_>, buf: &mut Vec<u8>) -> io::Result<()> {
+        let level = record.level;
+        let level_name = (self.get_level_name)(level);
+        let mut global_context = record.context.clone();
+
+        if self.static_fields && !context.static_fields.is_empty() {
+            let fields: Vec<String> = context.static_fields.iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+            global_context = format!("[{}] {global_context}", fields.join(" "));
+        }
+
+        let layout = self.layout.get(context.terminal_width, || {
+            let aligned_level_names = self.align_level_names.then_some(self.get_level_name);
+            Layout::new(context.terminal_width, aligned_level_names, self.target.as_ref())
+        });
+        let mut metadata = String::new();
+
+        if self.detailed {
+            let time = if self.full_timestamps {
+                format!("[{}]", super::format_timestamp(record.time))
+            } else {
+                record.time.format("[%T%.3f]").to_string()
+            };
+            let location = match context.source_location {
+                SourceLocation::File => match (record.file, record.line) {
+                    (Some(file), Some(line)) => format!(" [{}]", layout.format_location(file, line)),
+                    _ => String::new(),
+                },
+                SourceLocation::Target => format!(" [{}]", record.target),
+                SourceLocation::Omit => String::new(),
+            };
+            metadata = format!("{time}{location} ");
+        }
+
+        if let Some(ref column) = self.target {
+            metadata = format!("{metadata}{} ", column.format(record.target, layout.target));
+        }
+
+        let style = record.style.unwrap_or_else(|| Style::from(get_level_color(level)));
+        let message = Highlighted {message: record.message, style, enabled: context.colored && self.highlight_values};
+
+        let metadata_width = metadata.chars().count();
+
+        match self.metadata_style {
+            Some(metadata_style) if context.colored => {
+                write!(buf, "{}{}", metadata_style.paint(metadata), style.prefix())?;
+            },
+            _ if context.colored => write!(buf, "{}{metadata}", style.prefix())?,
+            _ => write!(buf, "{metadata}")?,
+        }
+
+        let prefix = format!("{level_name:<level_width$}{global_context}", level_width=layout.level);
+
+        if self.indent_multiline {
+            let indent = " ".repeat(metadata_width + prefix.chars().count());
+            let message = message.to_string().replace('\n', &format!("\n{indent}"));
+            write!(buf, "{prefix}{message}")?;
+        } else {
+            write!(buf, "{prefix}{message}")?;
+        }
+
+        if context.colored {
+            write!(buf, "{}", style.suffix())?;
+        }
+
+        writeln!(buf)
+    }
+}
+
+fn get_level_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::Red,
+        Level::Warn  => Color::Yellow,
+        Level::Info  => Color::Green,
+        Level::Debug => Color::Cyan,
+        Level::Trace => Color::Purple,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Record;
+
+    use crate::encoder::tests::encode;
+
+    use super::*;
+
+    fn get_level_name(level: Level) -> &'static str {
+        match level {
+            Level::Error => "Error: ",
+            Level::Warn => "Warning: ",
+            _ => "",
+        }
+    }
+
+    fn lines(encoder: &TextEncoder) -> Vec<String> {
+        [Level::Error, Level::Warn, Level::Info].into_iter().map(|level| {
+            let record = Record::builder().level(level).target("test").args(format_args!("message")).build();
+            encode(encoder, &record, &[])
+        }).collect()
+    }
+
+    #[test]
+    fn level_names() {
+        let encoder = TextEncoder::new().compact().level_names(get_level_name);
+        assert_eq!(lines(&encoder), vec!["Error: message\n", "Warning: message\n", "message\n"]);
+
+        let encoder = TextEncoder::new().compact().level_names(get_level_name).align_level_names();
+        assert_eq!(lines(&encoder), vec!["Error:   message\n", "Warning: message\n", "         message\n"]);
+
+        let encoder = TextEncoder::new().compact().level_names(|_| "").align_level_names();
+        assert_eq!(lines(&encoder), vec!["message\n", "message\n", "message\n"]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::env;
+
+/// Overrides for everything the logger senses from the environment: TTY detection, `TERM`,
+/// `NO_COLOR` and systemd journal detection. Values which aren't overridden are detected on logger
+/// initialization, so specifying all of them makes the behavior fully deterministic.
+#[derive(Clone, Default)]
+pub struct EnvironmentOverrides {
+    stdout_tty: Option<bool>,
+    stderr_tty: Option<bool>,
+    term: Option<Option<String>>,
+    no_color: Option<bool>,
+    systemd: Option<bool>,
+    same_output: Option<bool>,
+}
+
+impl EnvironmentOverrides {
+    pub fn new() -> EnvironmentOverrides {
+        EnvironmentOverrides::default()
+    }
+
+    pub fn stdout_tty(mut self, tty: bool) -> Self {
+        self.stdout_tty = Some(tty);
+        self
+    }
+
+    pub fn stderr_tty(mut self, tty: bool) -> Self {
+        self.stderr_tty = Some(tty);
+        self
+    }
+
+    /// Overrides `TERM` environment variable (`None` means it's not set). Colors are disabled for
+    /// `dumb` terminals.
+    pub fn term(mut self, term: Option<&str>) -> Self {
+        self.term = Some(term.map(ToOwned::to_owned));
+        self
+    }
+
+    /// Overrides presence of `NO_COLOR` environment variable which disables colors.
+    pub fn no_color(mut self, no_color: bool) -> Self {
+        self.no_color = Some(no_color);
+        self
+    }
+
+    /// Overrides whether stderr is connected to systemd journal (detected via `JOURNAL_STREAM`).
+    /// Output to journal is never colored and all records are written to stdout to preserve their
+    /// order.
+    pub fn systemd(mut self, systemd: bool) -> Self {
+        self.systemd = Some(systemd);
+        self
+    }
+
+    /// Overrides whether stdout and stderr refer to the same file or pipe (`command &> log` for
+    /// example), in which case all records are written to stdout to preserve their order.
+    pub fn same_output(mut self, same: bool) -> Self {
+        self.same_output = Some(same);
+        self
+    }
+
+    pub(crate) fn resolve(&self) -> Environment {
+        let stdout_tty = self.stdout_tty.unwrap_or_else(|| atty::is(atty::Stream::Stdout));
+        let stderr_tty = self.stderr_tty.unwrap_or_else(|| atty::is(atty::Stream::Stderr));
+
+        Environment {
+            stdout_tty, stderr_tty,
+            term: self.term.clone().unwrap_or_else(|| env::var("TERM").ok()),
+            no_color: self.no_color.unwrap_or_else(|| {
+                env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+            }),
+            systemd: self.systemd.unwrap_or_else(is_journal_stream),
+            // Terminals are intentionally not taken into account
+            same_output: self.same_output.unwrap_or_else(|| !stdout_tty && !stderr_tty && is_same_output()),
+        }
+    }
+}
+
+pub(crate) struct Environment {
+    pub stdout_tty: bool,
+    pub stderr_tty: bool,
+    pub term: Option<String>,
+    pub no_color: bool,
+    pub systemd: bool,
+    pub same_output: bool,
+}
+
+impl Environment {
+    pub fn merged_output(&self) -> bool {
+        self.same_output || self.systemd
+    }
+
+    pub fn stdout_colored(&self) -> bool {
+        self.stdout_tty && self.colors_allowed()
+    }
+
+    pub fn stderr_colored(&self) -> bool {
+        self.stderr_tty && self.colors_allowed()
+    }
+
+    fn colors_allowed(&self) -> bool {
+        !self.no_color && !self.systemd && self.term.as_deref() != Some("dumb")
+    }
+}
+
+// systemd sets JOURNAL_STREAM to `device:inode` of the journal stream, so compare it with stderr to
+// not be confused by the variable inherited from a service by an interactive process.
+fn is_journal_stream() -> bool {
+    let Ok(stream) = env::var("JOURNAL_STREAM") else {
+        return false;
+    };
+
+    match get_file_id(Fd::Stderr) {
+        Some((device, inode)) => stream == format!("{device}:{inode}"),
+        None => false,
+    }
+}
+
+// Checks whether stdout and stderr refer to the same file or pipe
+fn is_same_output() -> bool {
+    match (get_file_id(Fd::Stdout), get_file_id(Fd::Stderr)) {
+        (Some(stdout), Some(stderr)) => stdout == stderr,
+        _ => false,
+    }
+}
+
+enum Fd {
+    Stdout,
+    Stderr,
+}
+
+#[cfg(unix)]
+fn get_file_id(fd: Fd) -> Option<(u64, u64)> {
+    let fd = match fd {
+        Fd::Stdout => libc::STDOUT_FILENO,
+        Fd::Stderr => libc::STDERR_FILENO,
+    };
+
+    unsafe {
+        let mut stat: libc::stat = std::mem::zeroed();
+        if libc::fstat(fd, &mut stat) == 0 {
+            #[allow(clippy::unnecessary_cast)]
+            Some((stat.st_dev as u64, stat.st_ino as u64))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn get_file_id(_fd: Fd) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,60 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+use log::SetLoggerError;
+
+/// An error returned by [`LoggingConfig::build()`](crate::LoggingConfig::build).
+///
+/// All errors except [`Error::SetLogger`] are returned only in
+/// [strict mode](crate::LoggingConfig::strict): otherwise the logger degrades gracefully.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The global logger has already been set
+    SetLogger(SetLoggerError),
+    /// Unable to open a log file
+    OpenFile {path: PathBuf, error: io::Error},
+    /// Invalid RUST_LOG-style directive string (with the parsing error)
+    InvalidDirectives(String),
+    /// Unknown console format chosen via the environment variable
+    UnknownFormat {variable: String, value: String},
+    /// The environment variable a static field refers to isn't set
+    MissingVariable {field: String, variable: String},
+    /// Malformed entry of the context inherited from the parent process
+    InvalidContext(String),
+    /// Unable to attach to the global logger since it's not configured by easy-logging
+    ForeignLogger,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::SetLogger(err) => write!(f, "{err}"),
+            Error::OpenFile {path, error} => write!(f, "Unable to open {path:?} log file: {error}"),
+            Error::InvalidDirectives(err) => write!(f, "{err}"),
+            Error::UnknownFormat {variable, value} => write!(
+                f, "Unknown log format in {variable} environment variable: {value:?}"),
+            Error::MissingVariable {field, variable} => write!(
+                f, "{variable} environment variable of {field:?} static field is not set"),
+            Error::InvalidContext(entry) => write!(f, "Invalid inherited logging context entry: {entry:?}"),
+            Error::ForeignLogger => write!(f, "Unable to attach to the global logger: it's not configured by easy-logging"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::SetLogger(err) => Some(err),
+            Error::OpenFile {error, ..} => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<SetLoggerError> for Error {
+    fn from(err: SetLoggerError) -> Self {
+        Error::SetLogger(err)
+    }
+}
@@ -0,0 +1,83 @@
+use std::cell::Cell;
+use std::panic;
+use std::sync::{Arc, Mutex, Once, RwLockReadGuard};
+
+use lazy_static::lazy_static;
+
+use crate::fork::FORK_LOCK;
+use crate::handle::State;
+
+lazy_static! {
+    static ref STATES: Mutex<Vec<Arc<State>>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    static IN_LOGGER: Cell<bool> = const { Cell::new(false) };
+}
+
+// Registers best-effort flush handlers for normal process exit and panics
+pub(crate) fn flush_on_exit(state: Arc<State>) {
+    static INSTALL: Once = Once::new();
+
+    STATES.lock().unwrap().push(state);
+
+    INSTALL.call_once(|| {
+        #[cfg(unix)] {
+            extern "C" fn on_exit() {
+                flush();
+            }
+            unsafe { libc::atexit(on_exit); }
+        }
+
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous_hook(info);
+
+            // If the panic has happened inside of the logger, its locks may be held by the current
+            // thread, so we can't safely flush the sinks.
+            if !LoggerScope::is_active() {
+                flush();
+            }
+        }));
+    });
+}
+
+fn flush() {
+    // Don't block forever if exit or panic happens while the registry is being modified
+    if let Ok(states) = STATES.try_lock() {
+        for state in states.iter() {
+            state.flush();
+        }
+    }
+}
+
+// Marks the current thread as executing the logger code
+pub(crate) struct LoggerScope {
+    previous: bool,
+    _fork_lock: Option<RwLockReadGuard<'static, ()>>,
+}
+
+impl LoggerScope {
+    pub fn enter() -> LoggerScope {
+        let previous = IN_LOGGER.with(|in_logger| in_logger.replace(true));
+
+        // Only the outermost scope takes the lock to not deadlock on recursive logging
+        let fork_lock = if previous {
+            None
+        } else {
+            Some(FORK_LOCK.read().unwrap_or_else(|err| err.into_inner()))
+        };
+
+        LoggerScope {previous, _fork_lock: fork_lock}
+    }
+
+    pub fn is_active() -> bool {
+        IN_LOGGER.with(Cell::get)
+    }
+}
+
+impl Drop for LoggerScope {
+    fn drop(&mut self) {
+        IN_LOGGER.with(|in_logger| in_logger.set(self.previous));
+    }
+}
@@ -0,0 +1,547 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use log::{Level, LevelFilter};
+
+use crate::buffer::ErrorContextBuffer;
+use crate::crc;
+use crate::encoder::{Encoder, TimeZone};
+use crate::transport::{SinkConfig, Transport};
+
+pub struct FileSink {
+    path: PathBuf,
+    shared: bool,
+    locked: bool,
+    per_thread: bool,
+    level: LevelFilter,
+    error_context: Option<(Duration, usize)>,
+    heartbeat: Option<Duration>,
+    crc: bool,
+    time_zone: Option<TimeZone>,
+    durability: Durability,
+    encoder: Option<Box<dyn Encoder>>,
+}
+
+impl FileSink {
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileSink {
+        FileSink {
+            path: path.into(),
+            shared: false,
+            locked: false,
+            per_thread: false,
+            level: LevelFilter::Trace,
+            error_context: None,
+            heartbeat: None,
+            crc: false,
+            time_zone: None,
+            durability: Durability::None,
+            encoder: None,
+        }
+    }
+
+    /// Makes the file safe to be shared between several processes: each record is emitted with a
+    /// single write() to a file opened with O_APPEND, so lines of different processes never
+    /// interleave.
+    pub fn shared(mut self) -> Self {
+        self.shared = true;
+        self
+    }
+
+    /// Additionally guards each write with an advisory lock (flock) for the file systems where
+    /// O_APPEND alone doesn't guarantee atomicity (NFS for example).
+    pub fn locked(mut self) -> Self {
+        self.shared = true;
+        self.locked = true;
+        self
+    }
+
+    /// Writes records of each thread to its own file named after the thread name and ID
+    /// (`app.worker-3.log` for `app.log` path and `worker` thread with ID 3), so file writes of
+    /// different threads don't contend with each other.
+    pub fn per_thread(mut self) -> Self {
+        self.per_thread = true;
+        self
+    }
+
+    /// Writes only records of the specified level and more severe ones to the file.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level.to_level_filter();
+        self
+    }
+
+    /// Holds Debug and Trace records in a ring buffer of the specified capacity instead of writing
+    /// them. When an error occurs, the buffered records which are not older than the window are
+    /// written before it as a preceding context.
+    ///
+    /// The records are captured for all targets enabled by the logger even if their level is less
+    /// verbose, so the logger level and the other sinks aren't affected.
+    pub fn error_context(mut self, window: Duration, capacity: usize) -> Self {
+        self.error_context = Some((window, capacity));
+        self
+    }
+
+    /// Writes a "(no log output for 15m 0s, still alive, pid 123)" Info record when nothing has been
+    /// written to the file for the specified interval.
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = Some(interval);
+        self
+    }
+
+    /// Appends CRC32 of each record to its last line (`... #crc32=1a2b3c4d`), so truncated or
+    /// corrupted records can be detected by tooling. Each record is emitted with a single write()
+    /// regardless of this option, so a crash never leaves more than one partial record.
+    pub fn crc(mut self) -> Self {
+        self.crc = true;
+        self
+    }
+
+    /// Overrides the time zone of the timestamps (see [`LoggingConfig::time_zone()`]).
+    ///
+    /// [`LoggingConfig::time_zone()`]: crate::LoggingConfig::time_zone
+    pub fn time_zone(mut self, time_zone: TimeZone) -> Self {
+        self.time_zone = Some(time_zone);
+        self
+    }
+
+    /// Sets when the written records are synced to the storage device (see [`Durability`]).
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Sets the encoder for the file. Detailed text format is used by default.
+    pub fn encoder<E: Encoder + 'static>(mut self, encoder: E) -> Self {
+        self.encoder = Some(Box::new(encoder));
+        self
+    }
+
+    pub(crate) fn open(self) -> io::Result<SinkConfig> {
+        if self.per_thread {
+            static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+            let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+
+            // The files are opened lazily by the threads, so open the current thread's one in
+            // advance to report an unwritable path on logger initialization
+            let file = ThreadFile::open(&self.path, 0)?;
+            let opened = vec![Arc::downgrade(&file.file)];
+            let _ = THREAD_FILES.try_with(|files| files.borrow_mut().insert(id, file));
+
+            let writer = FileWriter::PerThread {id, generation: AtomicU64::new(0), opened: Mutex::new(opened)};
+
+            return Ok(self.into_config(writer));
+        }
+
+        let file = open_file(&self.path)?;
+
+        let writer = if self.shared {
+            FileWriter::Shared {file: RwLock::new(file), locked: self.locked}
+        } else {
+            FileWriter::Buffered(Mutex::new(BufWriter::new(file)))
+        };
+
+        Ok(self.into_config(writer))
+    }
+
+    fn into_config(self, writer: FileWriter) -> SinkConfig {
+        let durability = self.durability;
+
+        let transport = Arc::new(FileTransport {
+            path: self.path, writer,
+            crc: self.crc,
+            durability,
+            dirty: AtomicBool::new(false),
+        });
+
+        if durability == Durability::FsyncEverySecond {
+            let transport = Arc::downgrade(&transport);
+            thread::spawn(move || loop {
+                thread::sleep(SYNC_INTERVAL);
+
+                let Some(transport) = transport.upgrade() else {
+                    return;
+                };
+
+                if transport.dirty.swap(false, Ordering::Relaxed) {
+                    let _ = transport.sync();
+                }
+            });
+        }
+
+        let mut config = SinkConfig::new(transport, self.encoder);
+        config.level = self.level;
+        config.error_context = self.error_context.map(|(window, capacity)| {
+            Arc::new(ErrorContextBuffer::new(window, capacity))
+        });
+        config.heartbeat = self.heartbeat;
+        config.time_zone = self.time_zone;
+
+        config
+    }
+
+    pub(crate) fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub(crate) fn is_per_thread(&self) -> bool {
+        self.per_thread
+    }
+}
+
+/// Defines when records written to a file are synced to the storage device. Without syncing
+/// they reach only the OS page cache and may be lost on power loss.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Durability {
+    /// Leave syncing to the OS
+    None,
+    /// Sync the file in background at most once a second if it has been written
+    FsyncEverySecond,
+    /// Sync the file after each record
+    FsyncEachRecord,
+}
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(crate) struct FileTransport {
+    path: PathBuf,
+    writer: FileWriter,
+    crc: bool,
+    durability: Durability,
+    // Set when there are records which haven't been synced yet
+    dirty: AtomicBool,
+}
+
+enum FileWriter {
+    Buffered(Mutex<BufWriter<File>>),
+    Shared {file: RwLock<File>, locked: bool},
+    // Files are stored in thread local storage and reopened when generation is changed. The
+    // opened files are tracked to be synced from the other threads.
+    PerThread {id: usize, generation: AtomicU64, opened: Mutex<Vec<Weak<SyncedFile>>>},
+}
+
+thread_local! {
+    static THREAD_FILES: RefCell<HashMap<usize, ThreadFile>> = RefCell::new(HashMap::new());
+}
+
+struct ThreadFile {
+    generation: u64,
+    file: Arc<SyncedFile>,
+}
+
+impl ThreadFile {
+    fn open(path: &Path, generation: u64) -> io::Result<ThreadFile> {
+        let file = open_file(&get_thread_path(path))?;
+        Ok(ThreadFile {generation, file: Arc::new(SyncedFile {file, dirty: AtomicBool::new(false)})})
+    }
+}
+
+struct SyncedFile {
+    file: File,
+    // Set when there are records which haven't been synced yet
+    dirty: AtomicBool,
+}
+
+impl SyncedFile {
+    fn sync(&self) -> io::Result<()> {
+        if self.dirty.swap(false, Ordering::Relaxed) {
+            self.file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+// The file is closed on thread exit or reopening, so the records written to it are synced before
+impl Drop for SyncedFile {
+    fn drop(&mut self) {
+        let _ = self.sync();
+    }
+}
+
+impl FileTransport {
+    fn sync(&self) -> io::Result<()> {
+        match self.writer {
+            FileWriter::Buffered(ref writer) => {
+                let mut writer = writer.lock().unwrap();
+                writer.flush()?;
+                writer.get_ref().sync_data()
+            },
+            FileWriter::Shared {ref file, ..} => file.read().unwrap().sync_data(),
+            FileWriter::PerThread {ref opened, ..} => {
+                let mut result = Ok(());
+
+                // The files of the finished threads are closed, so forget about them
+                opened.lock().unwrap().retain(|file| match file.upgrade() {
+                    Some(file) => {
+                        if let Err(err) = file.sync() {
+                            result = Err(err);
+                        }
+                        true
+                    },
+                    None => false,
+                });
+
+                result
+            },
+        }
+    }
+
+    fn write_thread_file(
+        &self, files: &mut HashMap<usize, ThreadFile>, id: usize, generation: u64,
+        opened: &Mutex<Vec<Weak<SyncedFile>>>, data: &[u8],
+    ) -> io::Result<()> {
+        let file = match files.get_mut(&id) {
+            Some(file) if file.generation == generation => file,
+            _ => {
+                let file = ThreadFile::open(&self.path, generation)?;
+                opened.lock().unwrap().push(Arc::downgrade(&file.file));
+
+                files.insert(id, file);
+
+                files.get_mut(&id).unwrap()
+            },
+        };
+
+        let file = &file.file;
+        (&file.file).write_all(data)?;
+
+        match self.durability {
+            Durability::None => Ok(()),
+            Durability::FsyncEverySecond => {
+                file.dirty.store(true, Ordering::Relaxed);
+                self.dirty.store(true, Ordering::Relaxed);
+                Ok(())
+            },
+            Durability::FsyncEachRecord => file.file.sync_data(),
+        }
+    }
+
+    fn on_written(&self, file: &File) -> io::Result<()> {
+        match self.durability {
+            Durability::None => Ok(()),
+            Durability::FsyncEverySecond => {
+                self.dirty.store(true, Ordering::Relaxed);
+                Ok(())
+            },
+            Durability::FsyncEachRecord => file.sync_data(),
+        }
+    }
+}
+
+impl Transport for FileTransport {
+    fn write(&self, _level: Level, data: &[u8]) -> io::Result<()> {
+        let with_crc;
+        let data = if self.crc {
+            with_crc = append_crc(data);
+            &with_crc
+        } else {
+            data
+        };
+
+        match self.writer {
+            FileWriter::Buffered(ref writer) => {
+                // The buffer is flushed after each record, so it's always empty here and the record is
+                // emitted with a single write() either way
+                let mut writer = writer.lock().unwrap();
+                writer.write_all(data)?;
+                writer.flush()?;
+                self.on_written(writer.get_ref())
+            },
+            FileWriter::Shared {ref file, locked} => {
+                let file = file.read().unwrap();
+                let _lock = if locked {
+                    Some(FileLock::new(&file))
+                } else {
+                    None
+                };
+                // Records are always encoded in advance, so they are emitted with a single call
+                (&*file).write_all(data)?;
+                self.on_written(&file)
+            },
+            FileWriter::PerThread {id, ref generation, ref opened} => {
+                let generation = generation.load(Ordering::Relaxed);
+
+                match THREAD_FILES.try_with(|files| {
+                    self.write_thread_file(&mut files.borrow_mut(), id, generation, opened, data)
+                }) {
+                    Ok(result) => result,
+                    // The thread local storage is being destroyed (a record logged from a
+                    // destructor of another thread local), so write without caching the file
+                    Err(_) => {
+                        let mut file = open_file(&get_thread_path(&self.path))?;
+                        file.write_all(data)?;
+                        if self.durability != Durability::None {
+                            file.sync_data()?;
+                        }
+                        Ok(())
+                    },
+                }
+            },
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        if self.durability != Durability::None {
+            return self.sync();
+        }
+
+        match self.writer {
+            FileWriter::Buffered(ref writer) => writer.lock().unwrap().flush(),
+            FileWriter::Shared {..} | FileWriter::PerThread {..} => Ok(()),
+        }
+    }
+
+    // Reopens the file to make the logger write to the new file after log rotation
+    fn reopen(&self) -> io::Result<()> {
+        if let FileWriter::PerThread {ref generation, ..} = self.writer {
+            generation.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let file = open_file(&self.path)?;
+
+        match self.writer {
+            FileWriter::Buffered(ref writer) => {
+                let mut writer = writer.lock().unwrap();
+                let _ = writer.flush();
+                *writer = BufWriter::new(file);
+            },
+            FileWriter::Shared {file: ref current, ..} => {
+                *current.write().unwrap() = file;
+            },
+            FileWriter::PerThread {..} => unreachable!(),
+        }
+
+        Ok(())
+    }
+}
+
+fn append_crc(data: &[u8]) -> Vec<u8> {
+    let record = data.strip_suffix(b"\n").unwrap_or(data);
+
+    let mut result = Vec::with_capacity(record.len() + 17);
+    result.extend_from_slice(record);
+    result.extend_from_slice(format!(" #crc32={:08x}\n", crc::crc32(record)).as_bytes());
+
+    result
+}
+
+fn open_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+// Thread names aren't unique, so the file is named after both the thread name and its ID
+fn get_thread_path(path: &Path) -> PathBuf {
+    let current = thread::current();
+    let thread_name = current.name().unwrap_or("thread").replace(|c: char| c == '/' || c.is_whitespace(), "_");
+    let thread_id: String = format!("{:?}", current.id()).chars().filter(char::is_ascii_digit).collect();
+    let name = format!("{thread_name}-{thread_id}");
+
+    let mut file_name = path.file_stem().unwrap_or_default().to_owned();
+    file_name.push(".");
+    file_name.push(name);
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+
+    path.with_file_name(file_name)
+}
+
+struct FileLock<'a> {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    file: &'a File,
+}
+
+impl<'a> FileLock<'a> {
+    fn new(file: &'a File) -> FileLock<'a> {
+        #[cfg(unix)] {
+            use std::os::unix::io::AsRawFd;
+            unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX); }
+        }
+        FileLock {file}
+    }
+}
+
+impl Drop for FileLock<'_> {
+    fn drop(&mut self) {
+        #[cfg(unix)] {
+            use std::os::unix::io::AsRawFd;
+            unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_UN); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_paths() {
+        let get_path = |name: Option<&str>| {
+            let mut builder = thread::Builder::new();
+            if let Some(name) = name {
+                builder = builder.name(name.to_owned());
+            }
+            builder.spawn(|| get_thread_path(Path::new("logs/app.log"))).unwrap().join().unwrap()
+        };
+
+        let (first, second) = (get_path(Some("worker pool")), get_path(Some("worker pool")));
+        assert_ne!(first, second);
+
+        for path in [first, second, get_path(None)] {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            let thread = name.strip_prefix("app.").unwrap().strip_suffix(".log").unwrap();
+            let (name, id) = thread.rsplit_once('-').unwrap();
+
+            assert!(name == "worker_pool" || name == "thread", "{path:?}");
+            assert!(id.parse::<u64>().is_ok(), "{path:?}");
+            assert_eq!(path.parent(), Some(Path::new("logs")));
+        }
+    }
+
+    #[test]
+    fn per_thread_sync() {
+        let directory = std::env::temp_dir().join(format!("easy-logging-per-thread-sync-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir(&directory).unwrap();
+
+        let transport = Arc::new(FileTransport {
+            path: directory.join("app.log"),
+            writer: FileWriter::PerThread {id: usize::MAX, generation: AtomicU64::new(0), opened: Mutex::new(Vec::new())},
+            crc: false,
+            durability: Durability::FsyncEverySecond,
+            dirty: AtomicBool::new(false),
+        });
+        let FileWriter::PerThread {ref opened, ..} = transport.writer else {
+            unreachable!();
+        };
+        let dirty = || opened.lock().unwrap().iter()
+            .map(|file| file.upgrade().unwrap().dirty.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+
+        transport.write(Level::Info, b"main\n").unwrap();
+        {
+            let transport = transport.clone();
+            thread::spawn(move || transport.write(Level::Info, b"thread\n").unwrap()).join().unwrap();
+        }
+        assert_eq!(opened.lock().unwrap().len(), 2);
+        assert!(transport.dirty.load(Ordering::Relaxed));
+
+        // The file of the finished thread is forgotten
+        transport.flush().unwrap();
+        assert_eq!(dirty(), vec![false]);
+
+        transport.write(Level::Info, b"main\n").unwrap();
+        assert_eq!(dirty(), vec![true]);
+        transport.flush().unwrap();
+        assert_eq!(dirty(), vec![false]);
+
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
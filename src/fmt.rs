@@ -0,0 +1,106 @@
+//! Helpers for consistent human-readable formatting of values in log messages.
+
+use std::time::Duration;
+
+/// Formats the duration as `15µs`, `150ms`, `1.2s`, `3m 12s`, `2h 5m` or `3d 4h`.
+pub fn duration(duration: Duration) -> String {
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+
+    if duration < Duration::from_millis(1) {
+        return format!("{}µs", duration.as_micros());
+    } else if duration < Duration::from_secs(1) {
+        return format!("{}ms", duration.as_millis());
+    } else if duration < Duration::from_secs(MINUTE) {
+        return format!("{:.1}s", duration.as_secs_f64());
+    }
+
+    let seconds = duration.as_secs();
+
+    if seconds < HOUR {
+        format!("{}m {}s", seconds / MINUTE, seconds % MINUTE)
+    } else if seconds < DAY {
+        format!("{}h {}m", seconds / HOUR, seconds % HOUR / MINUTE)
+    } else {
+        format!("{}d {}h", seconds / DAY, seconds % DAY / HOUR)
+    }
+}
+
+/// Formats the size in binary units: `512 B`, `3.5 KiB`, `1.2 GiB`.
+pub fn bytes(size: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if size < 1024 {
+        return format!("{size} B");
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Formats the number with thousands separators: `1,234,567`.
+pub fn count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index != 0 && (digits.len() - index).is_multiple_of(3) {
+            result.push(',');
+        }
+        result.push(digit);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        for (value, expected) in [
+            (Duration::from_micros(15), "15µs"),
+            (Duration::from_millis(150), "150ms"),
+            (Duration::from_millis(1250), "1.2s"),
+            (Duration::from_secs(192), "3m 12s"),
+            (Duration::from_secs(2 * 3600 + 5 * 60 + 7), "2h 5m"),
+            (Duration::from_secs(3 * 86400 + 4 * 3600 + 5), "3d 4h"),
+        ] {
+            assert_eq!(duration(value), expected);
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        for (value, expected) in [
+            (0, "0 B"),
+            (512, "512 B"),
+            (3584, "3.5 KiB"),
+            (1288490189, "1.2 GiB"),
+            (u64::MAX, "16.0 EiB"),
+        ] {
+            assert_eq!(bytes(value), expected);
+        }
+    }
+
+    #[test]
+    fn counts() {
+        for (value, expected) in [
+            (0, "0"),
+            (999, "999"),
+            (1000, "1,000"),
+            (1234567, "1,234,567"),
+        ] {
+            assert_eq!(count(value), expected);
+        }
+    }
+}
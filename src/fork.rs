@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use std::io::{self, StdoutLock};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use lazy_static::lazy_static;
+
+use crate::context;
+use crate::exit::LoggerScope;
+use crate::handle::{Levels, State};
+use crate::transport::OUTPUT_MUTEX;
+
+// Held for reading by all threads executing the logger code and for writing during fork
+pub(crate) static FORK_LOCK: RwLock<()> = RwLock::new(());
+
+// Incremented in the child process after fork, so the sinks reopen their transports on first use
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    // The states are never unregistered, so they are leaked to be able to hold their locks during
+    // fork
+    static ref STATES: Mutex<Vec<&'static State>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    static FORK_GUARDS: RefCell<Option<ForkGuards>> = const { RefCell::new(None) };
+}
+
+// Locks held by the forking thread until the fork is finished (released in reverse order)
+struct ForkGuards {
+    _stdout: StdoutLock<'static>,
+    _output: MutexGuard<'static, ()>,
+    _levels: Vec<RwLockWriteGuard<'static, Levels>>,
+    _context: MutexGuard<'static, context::GlobalContextState>,
+    _logger: RwLockWriteGuard<'static, ()>,
+}
+
+pub(crate) fn register(state: Arc<State>) {
+    let state: &'static Arc<State> = Box::leak(Box::new(state));
+    STATES.lock().unwrap().push(state);
+}
+
+pub(crate) fn generation() -> u64 {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// Prepares the logger for `fork()`: flushes all sinks and waits until other threads leave the
+/// logger blocking them until [`after_fork_parent()`] or [`after_fork_child()`] is called, so the
+/// child doesn't inherit the logger locks held by threads which don't exist in it.
+///
+/// Must be followed by one of the `after_fork_*()` functions in the same thread. See also
+/// [`install_fork_handlers()`].
+pub fn prepare_fork() {
+    // The logger locks are held by the current thread (fork from inside of a sink), so there is
+    // nothing we can do
+    if LoggerScope::is_active() {
+        return;
+    }
+
+    for state in STATES.lock().unwrap().iter() {
+        state.flush();
+    }
+
+    // The locks are taken in the same order as the logger code takes them
+    let logger = FORK_LOCK.write().unwrap_or_else(|err| err.into_inner());
+    let context = context::lock_global_context();
+    let levels = STATES.lock().unwrap().iter().map(|state| state.levels.lock()).collect();
+    let output = OUTPUT_MUTEX.lock().unwrap_or_else(|err| err.into_inner());
+    let stdout = io::stdout().lock();
+
+    FORK_GUARDS.with(|guards| {
+        guards.borrow_mut().replace(ForkGuards {
+            _stdout: stdout,
+            _output: output,
+            _levels: levels,
+            _context: context,
+            _logger: logger,
+        });
+    });
+}
+
+/// Resumes logging in the parent process after `fork()`.
+pub fn after_fork_parent() {
+    FORK_GUARDS.with(|guards| guards.borrow_mut().take());
+}
+
+/// Resumes logging in the child process after `fork()`. All log files (and other transports) are
+/// reopened on their first use in the child, so it doesn't share buffers and file positions with
+/// the parent. The function itself doesn't allocate or open files, so it's safe to call it from
+/// `pthread_atfork()` child handler.
+///
+/// Please note that background threads of the logger (heartbeats, level reverting, directives file
+/// watching, burst limit summaries) don't exist in the child.
+pub fn after_fork_child() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    FORK_GUARDS.with(|guards| guards.borrow_mut().take());
+}
+
+/// Registers [`prepare_fork()`], [`after_fork_parent()`] and [`after_fork_child()`] as
+/// `pthread_atfork()` handlers, so they are called automatically on each `fork()`.
+#[cfg(unix)]
+pub fn install_fork_handlers() {
+    use std::sync::Once;
+
+    static INSTALL: Once = Once::new();
+
+    extern "C" fn prepare() {
+        prepare_fork();
+    }
+
+    extern "C" fn parent() {
+        after_fork_parent();
+    }
+
+    extern "C" fn child() {
+        after_fork_child();
+    }
+
+    INSTALL.call_once(|| unsafe {
+        libc::pthread_atfork(Some(prepare), Some(parent), Some(child));
+    });
+}
@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::error::Error;
+use crate::exit::LoggerScope;
+use crate::handle::State;
+use crate::logger::EasyLogger;
+use crate::messages;
+use crate::pipeline;
+
+// The global logger which dispatches records to all loggers built by the crate, so other configs
+// can be attached to it
+static LOGGER: GlobalLogger = GlobalLogger {
+    loggers: RwLock::new(Vec::new()),
+    early_records: Mutex::new(None),
+};
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+const MAX_EARLY_RECORDS: usize = 1000;
+
+struct GlobalLogger {
+    loggers: RwLock<Vec<EasyLogger>>,
+    // Records emitted before the logger is built (if their capturing is enabled)
+    early_records: Mutex<Option<EarlyRecords>>,
+}
+
+#[derive(Default)]
+struct EarlyRecords {
+    records: VecDeque<EarlyRecord>,
+    dropped: usize,
+}
+
+impl EarlyRecords {
+    fn push(&mut self, record: &Record) {
+        if self.records.len() >= MAX_EARLY_RECORDS {
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+        self.records.push_back(EarlyRecord::new(record));
+    }
+
+    fn replay(self, logger: &EasyLogger) {
+        if self.dropped != 0 {
+            let message = messages::get().early_records_dropped(self.dropped);
+
+            // Emitted with the application target to not be filtered out by its levels
+            logger.log(&Record::builder()
+                .level(Level::Warn)
+                .target(&logger.module_name())
+                .args(format_args!("{message}"))
+                .build());
+        }
+
+        for record in &self.records {
+            record.replay(logger);
+        }
+    }
+}
+
+struct EarlyRecord {
+    level: Level,
+    target: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    message: String,
+}
+
+impl EarlyRecord {
+    fn new(record: &Record) -> EarlyRecord {
+        EarlyRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            module_path: record.module_path().map(ToOwned::to_owned),
+            file: record.file().map(ToOwned::to_owned),
+            line: record.line(),
+            message: pipeline::format_safely(*record.args()),
+        }
+    }
+
+    fn replay(&self, logger: &EasyLogger) {
+        let metadata = Metadata::builder().level(self.level).target(&self.target).build();
+        if !logger.enabled(&metadata) {
+            return;
+        }
+
+        logger.log(&Record::builder()
+            .metadata(metadata)
+            .module_path(self.module_path.as_deref())
+            .file(self.file.as_deref())
+            .line(self.line)
+            .args(format_args!("{}", self.message))
+            .build());
+    }
+}
+
+impl Log for GlobalLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let loggers = self.loggers.read().unwrap();
+        if loggers.is_empty() {
+            return self.early_records.lock().unwrap().is_some();
+        }
+        loggers.iter().any(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record) {
+        {
+            let loggers = self.loggers.read().unwrap();
+            if !loggers.is_empty() {
+                for logger in loggers.iter() {
+                    logger.log(record);
+                }
+                return;
+            }
+        }
+
+        // Early records are being replayed by the current thread
+        if LoggerScope::is_active() {
+            return;
+        }
+
+        let mut early_records = self.early_records.lock().unwrap();
+
+        if let Some(early_records) = early_records.as_mut() {
+            early_records.push(record);
+            return;
+        }
+
+        // The logger has been built in the meantime
+        drop(early_records);
+        for logger in self.loggers.read().unwrap().iter() {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        for logger in self.loggers.read().unwrap().iter() {
+            logger.flush();
+        }
+    }
+}
+
+/// Installs the global logger which buffers records until [`LoggingConfig::build()`] is called and
+/// then replays them through the configured sinks, so records emitted by early code (configuration
+/// loading for example) aren't lost. Only the last 1000 records are kept and they are timestamped
+/// with the time of replay.
+///
+/// Does nothing if the global logger is already set.
+///
+/// [`LoggingConfig::build()`]: crate::LoggingConfig::build
+pub fn capture_early_records() {
+    let mut early_records = LOGGER.early_records.lock().unwrap();
+
+    if log::set_logger(&LOGGER).is_ok() {
+        INSTALLED.store(true, Ordering::SeqCst);
+        early_records.replace(EarlyRecords::default());
+        log::set_max_level(LevelFilter::Trace);
+    }
+}
+
+// Calls the callback to apply the global settings of the config only if the logger is installed
+pub(crate) fn install<F: FnOnce()>(
+    logger: EasyLogger, attach: bool, strict: bool, on_installed: F,
+) -> Result<(), Error> {
+    let mut early_records = LOGGER.early_records.lock().unwrap();
+
+    match log::set_logger(&LOGGER) {
+        Ok(()) => INSTALLED.store(true, Ordering::SeqCst),
+        Err(_) if early_records.is_some() => {},
+        Err(err) if !attach => return Err(err.into()),
+        Err(_) if INSTALLED.load(Ordering::SeqCst) => {},
+        Err(_) if strict => return Err(Error::ForeignLogger),
+        Err(_) => {
+            drop(early_records);
+            log::warn!("Unable to attach to the global logger: it's not configured by easy-logging. Logging is disabled.");
+            return Ok(());
+        },
+    }
+
+    on_installed();
+
+    // Replay the records before adding the logger to preserve their order: the new records will
+    // wait for the early records lock to be released.
+    if let Some(early) = early_records.take() {
+        let _scope = LoggerScope::enter();
+        early.replay(&logger);
+    }
+
+    let max_level = logger.max_level();
+    LOGGER.loggers.write().unwrap().push(logger);
+    drop(early_records);
+    set_max_level(max_level);
+
+    Ok(())
+}
+
+// Checks whether a logger has already been built, so the new configs can be only attached to it
+pub(crate) fn is_configured() -> bool {
+    !LOGGER.loggers.read().unwrap().is_empty()
+}
+
+// Returns state of the primary logger
+#[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+pub(crate) fn state() -> Option<Arc<State>> {
+    LOGGER.loggers.read().unwrap().first().map(EasyLogger::state)
+}
+
+// Returns module name of the application (the one the primary logger has been configured with)
+pub(crate) fn module_name() -> Option<String> {
+    LOGGER.loggers.read().unwrap().first().map(EasyLogger::module_name)
+}
+
+// The loggers pass all records to our runtime filters, so limit the global max level to avoid
+// formatting of the records which are going to be filtered out. If the global logger is installed,
+// the most verbose level of all attached loggers is used instead of the specified one.
+pub(crate) fn set_max_level(max_level: LevelFilter) {
+    if !INSTALLED.load(Ordering::SeqCst) {
+        log::set_max_level(max_level);
+        return;
+    }
+
+    let loggers = LOGGER.loggers.read().unwrap();
+    log::set_max_level(loggers.iter().map(EasyLogger::max_level).max().unwrap_or(max_level));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{Capture, LoggingConfig};
+
+    use super::*;
+
+    fn record(early_records: &mut EarlyRecords, level: Level, target: &str, message: &str) {
+        early_records.push(&Record::builder().level(level).target(target).args(format_args!("{message}")).build());
+    }
+
+    fn replay(early_records: EarlyRecords) -> Vec<(Level, String)> {
+        let capture = Capture::new();
+        let logger = LoggingConfig::new("test", Level::Info)
+            .console_writers(io::sink(), io::sink(), false)
+            .capture(&capture)
+            .logger();
+
+        early_records.replay(&logger);
+        capture.records().into_iter().map(|record| (record.level, record.message)).collect()
+    }
+
+    #[test]
+    fn replay_order() {
+        let mut early_records = EarlyRecords::default();
+
+        record(&mut early_records, Level::Info, "test", "first");
+        record(&mut early_records, Level::Debug, "test", "filtered out");
+        record(&mut early_records, Level::Error, "test::module", "second");
+        record(&mut early_records, Level::Warn, "test", "third");
+
+        assert_eq!(replay(early_records), vec![
+            (Level::Info, "first".to_owned()),
+            (Level::Error, "second".to_owned()),
+            (Level::Warn, "third".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn dropped_records() {
+        let mut early_records = EarlyRecords::default();
+        for index in 0..MAX_EARLY_RECORDS + 5 {
+            record(&mut early_records, Level::Info, "test", &index.to_string());
+        }
+        assert_eq!(early_records.dropped, 5);
+
+        let records = replay(early_records);
+        assert_eq!(records.len(), MAX_EARLY_RECORDS + 1);
+        assert_eq!(records[0], (Level::Warn, messages::get().early_records_dropped(5)));
+        assert_eq!(records[1], (Level::Info, "5".to_owned()));
+        assert_eq!(records.last().unwrap(), &(Level::Info, (MAX_EARLY_RECORDS + 4).to_string()));
+    }
+}
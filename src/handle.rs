@@ -0,0 +1,505 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use log::{Level, LevelFilter, Metadata};
+
+use crate::buffer::ErrorContextBuffer;
+#[cfg(feature = "bundle")]
+use crate::bundle;
+use crate::directives::{self, Directive};
+use crate::global;
+use crate::propagate;
+use crate::transport::Transport;
+
+/// Allows to change logging level at runtime after the logger is initialized.
+///
+/// Only filtering is affected: output format is chosen once on logger initialization.
+#[derive(Clone)]
+pub struct Handle {
+    state: Arc<State>,
+}
+
+impl Handle {
+    pub(crate) fn new(state: Arc<State>) -> Handle {
+        Handle {state}
+    }
+
+    pub fn level(&self) -> Level {
+        self.state.levels.read().level
+    }
+
+    pub fn set_level(&self, level: Level) {
+        self.state.levels.set_level(level);
+    }
+
+    /// Temporarily changes the logging level, reverting it back after the specified duration
+    /// unless it's changed again in the meantime by [`Handle::set_level()`] or
+    /// [`Handle::set_level_for()`]. Other changes (directives for example) don't cancel the revert.
+    pub fn set_level_for(&self, level: Level, duration: Duration) {
+        let (previous_level, generation) = self.state.levels.set_level(level);
+
+        let state = self.state.clone();
+        thread::spawn(move || {
+            thread::sleep(duration);
+            state.levels.restore_level(generation, previous_level);
+        });
+    }
+
+    /// Disables all sinks until [`Handle::unsilence()`] is called preserving all level settings (for
+    /// interactive password prompts for example). Audit records are still written, but not to the
+    /// console.
+    pub fn silence(&self) {
+        self.state.levels.set_silenced(true);
+    }
+
+    /// Restores logging disabled by [`Handle::silence()`].
+    pub fn unsilence(&self) {
+        self.state.levels.set_silenced(false);
+    }
+
+    /// Loads per-target levels from a file with RUST_LOG-style directives (`target=level` or just
+    /// `level` for all other targets) separated by commas or newlines. `#` starts a comment.
+    ///
+    /// The loaded directives replace all previously loaded ones.
+    pub fn reload_from_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+
+        let directives = directives::parse(&fs::read_to_string(path)?).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{path:?}: {err}"))
+        })?;
+
+        self.state.levels.update(|levels| levels.directives = directives);
+        Ok(())
+    }
+
+    /// Executes a log control command. Intended to be wired to application's admin endpoint.
+    ///
+    /// Supported commands:
+    /// * `level <directives>` - applies RUST_LOG-style directives on top of the current ones
+    /// * `flush` - flushes all sinks
+    /// * `rotate` - reopens log files (to be used after they're rotated by an external tool)
+    /// * `stats` - returns the number of emitted records per level
+    pub fn execute(&self, command: &str) -> Result<String, String> {
+        let command = command.trim();
+        let (name, args) = match command.split_once(char::is_whitespace) {
+            Some((name, args)) => (name, args.trim()),
+            None => (command, ""),
+        };
+
+        match (name, args) {
+            ("level", directives) if !directives.is_empty() => {
+                let directives = directives::parse(directives)?;
+
+                self.state.levels.update(|levels| {
+                    for directive in directives {
+                        levels.directives.retain(|existing| existing.target != directive.target);
+                        levels.directives.push(directive);
+                    }
+                });
+
+                Ok(String::new())
+            },
+            ("flush", "") => {
+                self.state.flush();
+                Ok(String::new())
+            },
+            ("rotate", "") => {
+                for transport in self.state.transports.lock().unwrap().iter() {
+                    transport.reopen().map_err(|err| format!("Failed to reopen log file: {err}"))?;
+                }
+                Ok(String::new())
+            },
+            ("stats", "") => Ok(self.state.stats.to_string()),
+            _ => Err(format!("Invalid command: {command:?}")),
+        }
+    }
+
+    /// Exports the current context and the static fields to the command environment (see
+    /// [`LoggingConfig::import_context()`](crate::LoggingConfig::import_context)), so the child
+    /// process preserves the logical operation context.
+    pub fn export_context(&self, command: &mut Command) {
+        propagate::export(command, &self.state.static_fields.lock().unwrap());
+    }
+
+    /// Writes a bundle with the logging configuration, recent records and log files for attaching
+    /// to bug reports (see [`export_bundle()`](crate::export_bundle)).
+    #[cfg(feature = "bundle")]
+    pub fn export_bundle<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        bundle::export(&self.state, path.as_ref())
+    }
+
+    /// Loads per-target levels from the specified file (see [`Handle::reload_from_file`]) and
+    /// starts a background thread which reloads them each time the file is changed.
+    #[cfg(feature = "watch")]
+    pub fn watch_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        use std::io::Write;
+
+        const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+        let path = path.as_ref().to_owned();
+        let mut modify_time = fs::metadata(&path)?.modified()?;
+        self.reload_from_file(&path)?;
+
+        let handle = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let current_modify_time = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                Ok(time) => time,
+                Err(_) => continue,
+            };
+
+            if current_modify_time != modify_time {
+                modify_time = current_modify_time;
+                if let Err(err) = handle.reload_from_file(&path) {
+                    let _ = writeln!(io::stderr(), "Failed to reload logging directives: {err}.");
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+pub(crate) struct State {
+    pub levels: LevelState,
+    pub transports: Mutex<Vec<Arc<dyn Transport>>>,
+    pub static_fields: Mutex<Vec<(String, String)>>,
+    pub files: Mutex<Vec<LogFile>>,
+    pub error_contexts: Mutex<Vec<Arc<ErrorContextBuffer>>>,
+    pub stats: Stats,
+}
+
+impl State {
+    pub fn new(module_name: Cow<'static, str>, level: Level) -> State {
+        State {
+            levels: LevelState::new(module_name, level),
+            transports: Mutex::new(Vec::new()),
+            static_fields: Mutex::new(Vec::new()),
+            files: Mutex::new(Vec::new()),
+            error_contexts: Mutex::new(Vec::new()),
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn flush(&self) {
+        for transport in self.transports.lock().unwrap().iter() {
+            let _ = transport.flush();
+        }
+    }
+}
+
+// A log file opened by the logger
+#[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+pub(crate) struct LogFile {
+    pub path: PathBuf,
+    // The records are written to per-thread shards of the file
+    pub per_thread: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct Stats {
+    records: [AtomicU64; 5],
+}
+
+impl Stats {
+    pub fn count(&self, level: Level) {
+        self.records[level as usize - 1].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (index, level) in Level::iter().enumerate() {
+            if index != 0 {
+                write!(f, " ")?;
+            }
+            let records = self.records[level as usize - 1].load(Ordering::Relaxed);
+            write!(f, "{}={}", level.as_str().to_lowercase(), records)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct LevelState {
+    levels: RwLock<Levels>,
+    // Cached max level to be able to get it without locking
+    max_level: AtomicUsize,
+    silenced: AtomicBool,
+    // Whether the changes are reflected in the global max level. It's not the case for the loggers
+    // which are installed by the application, since they may be composed with other loggers.
+    owns_max_level: AtomicBool,
+}
+
+pub(crate) struct Levels {
+    pub module_name: Cow<'static, str>,
+    pub level: Level,
+    // Directives specified on logger configuration
+    pub configured: Vec<Directive>,
+    // Level of the records with empty or `unknown` target (the default level is used if not set)
+    pub unknown_target_level: Option<LevelFilter>,
+    // The most verbose level of error context sinks: they receive records of this level for all
+    // enabled targets regardless of their levels
+    pub context_level: Option<LevelFilter>,
+    // Directives applied at runtime which take precedence over the configured ones
+    directives: Vec<Directive>,
+    // Incremented on each change of the level to not revert the temporary level changed afterwards
+    level_generation: u64,
+}
+
+impl LevelState {
+    pub fn new(module_name: Cow<'static, str>, level: Level) -> LevelState {
+        let levels = Levels {
+            module_name, level,
+            configured: Vec::new(),
+            unknown_target_level: None,
+            context_level: None,
+            directives: Vec::new(),
+            level_generation: 0,
+        };
+
+        LevelState {
+            max_level: AtomicUsize::new(levels.max_level() as usize),
+            levels: RwLock::new(levels),
+            silenced: AtomicBool::new(false),
+            owns_max_level: AtomicBool::new(true),
+        }
+    }
+
+    pub fn enabled(&self, metadata: &Metadata) -> bool {
+        !self.silenced.load(Ordering::Relaxed) && self.read().enabled(metadata)
+    }
+
+    pub fn is_silenced(&self) -> bool {
+        self.silenced.load(Ordering::Relaxed)
+    }
+
+    // Checks whether the record is enabled only for error context sinks
+    pub fn context_only(&self, metadata: &Metadata) -> bool {
+        let levels = self.read();
+        levels.context_only(metadata, levels.level_for(metadata.target()))
+    }
+
+    pub fn max_level(&self) -> LevelFilter {
+        if self.silenced.load(Ordering::Relaxed) {
+            return LevelFilter::Off;
+        }
+
+        let max_level = self.max_level.load(Ordering::Relaxed);
+        LevelFilter::iter().nth(max_level).unwrap()
+    }
+
+    pub fn module_name(&self) -> String {
+        self.read().module_name.clone().into_owned()
+    }
+
+    // Describes the current level settings in human-readable form
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    pub fn describe(&self) -> String {
+        let levels = self.read();
+
+        let directives: Vec<String> = levels.all_directives().map(|directive| match directive.target {
+            Some(ref target) => format!("{target}={}", directive.level),
+            None => directive.level.to_string(),
+        }).collect();
+
+        let mut description = format!("module: {}\nlevel: {}\ndirectives: {}\n",
+            levels.module_name, levels.level, directives.join(","));
+        if let Some(level) = levels.unknown_target_level {
+            description.push_str(&format!("unknown target level: {level}\n"));
+        }
+        if let Some(level) = levels.context_level {
+            description.push_str(&format!("error context level: {level}\n"));
+        }
+        if self.silenced.load(Ordering::Relaxed) {
+            description.push_str("silenced: true\n");
+        }
+
+        description
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Levels> {
+        self.levels.read().unwrap()
+    }
+
+    // Blocks all level changes and checks until the guard is released (used during fork)
+    pub fn lock(&'static self) -> RwLockWriteGuard<'static, Levels> {
+        self.levels.write().unwrap_or_else(|err| err.into_inner())
+    }
+
+    // Stops updating the global max level on changes
+    pub fn disown_max_level(&self) {
+        self.owns_max_level.store(false, Ordering::Relaxed);
+    }
+
+    // Changes the levels before logger initialization
+    pub fn configure<F: FnOnce(&mut Levels)>(&self, update: F) {
+        let mut levels = self.levels.write().unwrap();
+        update(&mut levels);
+        self.max_level.store(levels.max_level() as usize, Ordering::Relaxed);
+    }
+
+    fn update<F: FnOnce(&mut Levels)>(&self, update: F) {
+        let mut levels = self.levels.write().unwrap();
+        update(&mut levels);
+        self.on_updated(&levels);
+    }
+
+    // Returns the previous level and generation of the new one
+    fn set_level(&self, level: Level) -> (Level, u64) {
+        let mut levels = self.levels.write().unwrap();
+        let previous_level = std::mem::replace(&mut levels.level, level);
+        levels.level_generation += 1;
+        self.on_updated(&levels);
+        (previous_level, levels.level_generation)
+    }
+
+    fn restore_level(&self, generation: u64, level: Level) {
+        let mut levels = self.levels.write().unwrap();
+        if levels.level_generation == generation {
+            levels.level = level;
+            self.on_updated(&levels);
+        }
+    }
+
+    fn set_silenced(&self, silenced: bool) {
+        // Serialize with the updates to not get inconsistent global max level
+        let _levels = self.levels.write().unwrap();
+        self.silenced.store(silenced, Ordering::Relaxed);
+        self.update_max_level();
+    }
+
+    fn on_updated(&self, levels: &Levels) {
+        self.max_level.store(levels.max_level() as usize, Ordering::Relaxed);
+        self.update_max_level();
+    }
+
+    fn update_max_level(&self) {
+        if self.owns_max_level.load(Ordering::Relaxed) {
+            global::set_max_level(self.max_level());
+        }
+    }
+}
+
+impl Levels {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = self.level_for(metadata.target());
+        metadata.level() <= level || self.context_only(metadata, level)
+    }
+
+    // Error context sinks buffer only Debug and Trace records, so the widening doesn't affect the
+    // more severe ones
+    fn context_only(&self, metadata: &Metadata, level: LevelFilter) -> bool {
+        metadata.level() > level && metadata.level() >= Level::Debug && level != LevelFilter::Off &&
+            self.context_level.is_some_and(|context_level| metadata.level() <= context_level)
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        if let Some(level) = self.unknown_target_level {
+            if is_unknown_target(target) {
+                return level;
+            }
+        }
+
+        // The most specific target wins with directives taking precedence over the module level
+        let mut best: Option<(usize, LevelFilter)> = None;
+
+        if is_target_of(target, &self.module_name) {
+            best = Some((self.module_name.len(), self.level.to_level_filter()));
+        }
+
+        for directive in self.all_directives() {
+            if let Some(ref directive_target) = directive.target {
+                if is_target_of(target, directive_target) && best.is_none_or(|(length, _)| {
+                    directive_target.len() >= length
+                }) {
+                    best = Some((directive_target.len(), directive.level));
+                }
+            }
+        }
+
+        match best {
+            Some((_, level)) => level,
+            None => self.default_level(),
+        }
+    }
+
+    fn all_directives(&self) -> impl DoubleEndedIterator<Item = &Directive> {
+        self.configured.iter().chain(&self.directives)
+    }
+
+    fn default_level(&self) -> LevelFilter {
+        let directive = self.all_directives().rev().find(|directive| directive.target.is_none());
+        if let Some(directive) = directive {
+            return directive.level;
+        }
+
+        if self.level >= Level::Debug {
+            LevelFilter::Warn
+        } else {
+            LevelFilter::Off
+        }
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.all_directives().map(|directive| directive.level)
+            .chain([self.level.to_level_filter(), self.default_level()])
+            .chain(self.unknown_target_level)
+            .chain(self.context_level)
+            .max().unwrap()
+    }
+}
+
+// Generated code and FFI shims may emit records without a meaningful target
+pub(crate) fn is_unknown_target(target: &str) -> bool {
+    target.is_empty() || target == "unknown"
+}
+
+pub(crate) fn is_target_of(target: &str, module: &str) -> bool {
+    match target.strip_prefix(module) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle() -> Handle {
+        let state = State::new("test".into(), Level::Info);
+        // Don't interfere with other tests
+        state.levels.disown_max_level();
+        Handle::new(Arc::new(state))
+    }
+
+    #[test]
+    fn temporary_level() {
+        let handle = handle();
+
+        handle.set_level_for(Level::Trace, Duration::from_millis(50));
+        handle.execute("level other_crate=debug").unwrap();
+        assert_eq!(handle.level(), Level::Trace);
+
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(handle.level(), Level::Info);
+    }
+
+    #[test]
+    fn temporary_level_changed() {
+        let handle = handle();
+
+        handle.set_level_for(Level::Trace, Duration::from_millis(50));
+        handle.set_level(Level::Debug);
+
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(handle.level(), Level::Debug);
+    }
+}
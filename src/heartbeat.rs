@@ -0,0 +1,80 @@
+use std::sync::Weak;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use log::{Level, Record};
+
+use crate::clock;
+use crate::exit::LoggerScope;
+use crate::messages;
+use crate::transport::Sink;
+
+// Tracks activity of a sink to write a heartbeat record when it's quiet for too long, so idle
+// processes can be distinguished from the hung ones when tailing their logs.
+pub(crate) struct Heartbeat {
+    interval: Duration,
+    start_time: Duration,
+    // Milliseconds since start time
+    last_record: AtomicU64,
+    last_heartbeat: AtomicU64,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration) -> Heartbeat {
+        Heartbeat {
+            interval,
+            start_time: clock::monotonic(),
+            last_record: AtomicU64::new(0),
+            last_heartbeat: AtomicU64::new(0),
+        }
+    }
+
+    pub fn touch(&self) {
+        self.last_record.store(self.now(), Ordering::Relaxed);
+    }
+
+    fn now(&self) -> u64 {
+        clock::elapsed(self.start_time).as_millis() as u64
+    }
+
+    // Returns time since the last record and since the last record or heartbeat
+    fn idle_time(&self) -> (Duration, Duration) {
+        let now = self.now();
+        let last_record = self.last_record.load(Ordering::Relaxed);
+        let last_heartbeat = self.last_heartbeat.load(Ordering::Relaxed);
+
+        (
+            Duration::from_millis(now.saturating_sub(last_record)),
+            Duration::from_millis(now.saturating_sub(last_record.max(last_heartbeat))),
+        )
+    }
+}
+
+pub(crate) fn start(sink: Weak<Sink>) {
+    thread::spawn(move || loop {
+        let Some(sink) = sink.upgrade() else {
+            return;
+        };
+        let heartbeat = sink.heartbeat().unwrap();
+
+        let (idle_time, quiet_time) = heartbeat.idle_time();
+        if quiet_time < heartbeat.interval {
+            let sleep_time = heartbeat.interval - quiet_time;
+            drop(sink);
+            thread::sleep(sleep_time);
+            continue;
+        }
+
+        heartbeat.last_heartbeat.store(heartbeat.now(), Ordering::Relaxed);
+
+        let _scope = LoggerScope::enter();
+        let message = messages::get().heartbeat(idle_time, std::process::id());
+
+        sink.write_unobserved(&Record::builder()
+            .level(Level::Info)
+            .target("easy_logging")
+            .args(format_args!("{message}"))
+            .build());
+    });
+}
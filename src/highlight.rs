@@ -0,0 +1,142 @@
+use std::fmt::{self, Display, Formatter};
+
+use ansi_term::Style;
+
+const UNITS: &[&str] = &[
+    "ns", "us", "µs", "ms", "s", "m", "h", "d",
+    "B", "K", "M", "G", "T", "KB", "MB", "GB", "TB", "KiB", "MiB", "GiB", "TiB",
+    "%",
+];
+
+// Highlights numbers (optionally followed by a duration or byte size unit) and quoted strings in
+// the message which is expected to be rendered inside of the specified base style.
+pub struct Highlighted<'a, T: Display> {
+    pub message: &'a T,
+    pub style: Style,
+    pub enabled: bool,
+}
+
+impl<T: Display> Display for Highlighted<'_, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if !self.enabled {
+            return self.message.fmt(f);
+        }
+
+        let message = self.message.to_string();
+        let style = self.style.bold();
+        let mut position = 0;
+
+        while position < message.len() {
+            let rest = &message[position..];
+            let is_boundary = !message[..position].chars().next_back().is_some_and(is_word_char);
+
+            let value_length = if rest.starts_with('"') {
+                quoted_length(rest)
+            } else if is_boundary && rest.starts_with(|c: char| c.is_ascii_digit()) {
+                number_length(rest)
+            } else {
+                None
+            };
+
+            match value_length {
+                Some(length) => {
+                    write!(f, "{}{}{}{}", style.prefix(), &rest[..length], style.suffix(), self.style.prefix())?;
+                    position += length;
+                },
+                None => {
+                    let c = rest.chars().next().unwrap();
+                    write!(f, "{c}")?;
+                    position += c.len_utf8();
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn quoted_length(string: &str) -> Option<usize> {
+    let mut escaped = false;
+
+    for (index, c) in string.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(index + 1),
+            _ => {},
+        }
+    }
+
+    None
+}
+
+// Matches values like `12`, `0.5`, `1.2GiB`, `15%`, `3m12s` or `1.2.3`
+fn number_length(string: &str) -> Option<usize> {
+    let mut length = 0;
+
+    loop {
+        let rest = &string[length..];
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        if digits == 0 {
+            return None;
+        }
+        length += digits;
+
+        // Also consumes dotted sequences like versions or IP addresses
+        while let Some(fraction) = string[length..].strip_prefix('.') {
+            let digits = fraction.find(|c: char| !c.is_ascii_digit()).unwrap_or(fraction.len());
+            if digits == 0 {
+                break;
+            }
+            length += 1 + digits;
+        }
+
+        let rest = &string[length..];
+        let unit = rest.find(|c: char| !c.is_alphabetic() && c != '%').unwrap_or(rest.len());
+        if unit == 0 {
+            break;
+        } else if !UNITS.contains(&&rest[..unit]) {
+            return None;
+        }
+        length += unit;
+
+        if !string[length..].starts_with(|c: char| c.is_ascii_digit()) {
+            break;
+        }
+    }
+
+    if string[length..].chars().next().is_some_and(is_word_char) {
+        return None;
+    }
+
+    Some(length)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        for (string, length) in [
+            ("12", Some(2)),
+            ("12 records", Some(2)),
+            ("0.5,", Some(3)),
+            ("1.2GiB", Some(6)),
+            ("15%", Some(3)),
+            ("3m12s", Some(5)),
+            ("1.2.3.", Some(5)),
+            ("10.0.0.1:80", Some(8)),
+            ("12abc", None),
+            ("12_000", None),
+            ("x12", None),
+            ("", None),
+        ] {
+            assert_eq!(number_length(string), length, "{string:?}");
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::cell::Cell;
+use std::io::{self, Write};
+
+use log::{Level, Record};
+
+thread_local! {
+    static IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+pub(crate) type Callback = Box<dyn Fn(&Record) + Send + Sync>;
+
+// Callback invoked for emitted records of the specified level and more severe ones
+pub(crate) struct LevelHook {
+    level: Level,
+    callback: Callback,
+}
+
+impl LevelHook {
+    pub fn new(level: Level, callback: Callback) -> LevelHook {
+        LevelHook {level, callback}
+    }
+
+    // Records emitted by the hooks themselves don't trigger hooks to not recurse infinitely
+    pub fn call(&self, record: &Record) {
+        if record.level() > self.level || IN_HOOK.with(Cell::get) {
+            return;
+        }
+
+        let _scope = HookScope::enter();
+        (self.callback)(record);
+    }
+}
+
+struct HookScope;
+
+impl HookScope {
+    fn enter() -> HookScope {
+        IN_HOOK.with(|in_hook| in_hook.set(true));
+        HookScope
+    }
+}
+
+impl Drop for HookScope {
+    fn drop(&mut self) {
+        IN_HOOK.with(|in_hook| in_hook.set(false));
+    }
+}
+
+pub(crate) fn ring_bell(_record: &Record) {
+    let mut stderr = io::stderr();
+    let _ = stderr.write_all(b"\x07");
+    let _ = stderr.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn recursion() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static HOOK: OnceLock<LevelHook> = OnceLock::new();
+
+        let hook = HOOK.get_or_init(|| LevelHook::new(Level::Warn, Box::new(|record| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            HOOK.get().unwrap().call(record);
+        })));
+
+        for level in [Level::Info, Level::Warn, Level::Error] {
+            hook.call(&Record::builder().level(level).args(format_args!("message")).build());
+        }
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,37 +1,214 @@
+mod audit;
+mod buffer;
+#[cfg(feature = "bundle")]
+mod bundle;
+mod burst;
+mod capture;
+mod clock;
 mod context;
+mod crc;
+mod directives;
+mod emergency;
+mod encoder;
+mod environment;
+mod error;
+mod exit;
+mod file;
+mod fork;
+mod global;
+pub mod fmt;
+mod handle;
+mod heartbeat;
+mod highlight;
+mod hook;
+mod logger;
+mod messages;
+mod parts;
+mod pipeline;
+mod progress;
+mod propagate;
+mod render;
+#[cfg(feature = "tower")]
+mod request;
+mod span;
+mod style;
+mod terminal;
+mod transport;
+mod writer;
 
-use std::fmt;
+use std::borrow::Cow;
 use std::io::{self, Write};
-use std::sync::Mutex;
+use std::sync::Arc;
 
-use ansi_term::Color;
-use fern::{Dispatch, FormatCallback};
-use lazy_static::lazy_static;
-use log::{Level, LevelFilter, SetLoggerError};
+use fern::Dispatch;
+use log::{Level, LevelFilter};
 
+pub use ansi_term;
 pub use fern;
 pub use log;
-pub use crate::context::GlobalContext;
+pub use crate::audit::AUDIT_TARGET;
+#[doc(hidden)]
+pub use crate::audit::emit as __audit;
+#[cfg(feature = "bundle")]
+pub use crate::bundle::export_bundle;
+pub use crate::capture::{Capture, CapturedRecord, Query};
+pub use crate::clock::{Clock, SystemClock};
+pub use crate::context::{GlobalContext, ProgressContext};
+pub use crate::emergency::emergency;
+pub use crate::encoder::{EncodeContext, Encoder, JsonEncoder, LogfmtEncoder, SourceLocation, TargetColumn, TextEncoder, TimeZone, TimestampFormat};
+pub use crate::environment::EnvironmentOverrides;
+pub use crate::error::Error;
+pub use crate::file::{Durability, FileSink};
+pub use crate::fork::{after_fork_child, after_fork_parent, prepare_fork};
+#[cfg(unix)]
+pub use crate::fork::install_fork_handlers;
+pub use crate::global::capture_early_records;
+pub use crate::handle::Handle;
+pub use crate::logger::EasyLogger;
+pub use crate::messages::Messages;
+pub use crate::parts::{Filter, LoggerParts};
+pub use crate::progress::ProgressLogger;
+pub use crate::render::{FormatOptions, LogRecordData, format_record};
+#[cfg(feature = "tower")]
+pub use crate::request::{RequestContextLayer, RequestContextService};
+pub use crate::span::Span;
+pub use crate::style::styled;
+pub use crate::transport::Transport;
+pub use crate::writer::{LogWriter, RawStdout, RawStdoutLock, raw_stdout, writer};
+
+/// Checks whether a record with the specified level and target would be emitted by the logger.
+///
+/// Unlike `log_enabled!()` accepts arbitrary target and is evaluated at runtime. Levels disabled at
+/// compile time via `max-level-*` features are rejected without any runtime checks.
+#[inline]
+pub fn enabled(level: Level, target: &str) -> bool {
+    level <= log::STATIC_MAX_LEVEL && level <= log::max_level() && log::logger().enabled(
+        &log::Metadata::builder().level(level).target(target).build())
+}
+
+/// Logs the value returned by the closure only if the record is going to be emitted, so expensive
+/// argument construction is skipped otherwise.
+#[macro_export]
+macro_rules! lazy_log {
+    (target: $target:expr, $level:expr, $get:expr) => {
+        if $crate::enabled($level, $target) {
+            $crate::log::log!(target: $target, $level, "{}", ($get)());
+        }
+    };
+    ($level:expr, $get:expr) => {
+        $crate::lazy_log!(target: module_path!(), $level, $get)
+    };
+}
+
+#[macro_export]
+macro_rules! lazy_debug {
+    ($($args:tt)+) => {
+        $crate::lazy_log!($crate::log::Level::Debug, $($args)+)
+    };
+}
+
+#[macro_export]
+macro_rules! lazy_trace {
+    ($($args:tt)+) => {
+        $crate::lazy_log!($crate::log::Level::Trace, $($args)+)
+    };
+}
+
+/// Logs the expression and its pretty-printed `Debug` representation (`response = Response {...}`)
+/// with Debug level. The value isn't formatted if the record isn't going to be emitted.
+///
+/// The output is multi-line, so it's best combined with
+/// [`LoggingConfig::indent_multiline()`] and [`LoggingConfig::max_message_length()`].
+#[macro_export]
+macro_rules! debug_pretty {
+    (target: $target:expr, $value:expr) => {
+        $crate::lazy_log!(target: $target, $crate::log::Level::Debug, || {
+            format!("{} = {:#?}", stringify!($value), $value)
+        })
+    };
+    ($value:expr) => {
+        $crate::debug_pretty!(target: module_path!(), $value)
+    };
+}
+
+use crate::burst::BurstLimit;
+use crate::directives::Directive;
+use crate::handle::{LogFile, State};
+use crate::hook::LevelHook;
+use crate::parts::SinksByRole;
+use crate::pipeline::PipelineConfig;
+use crate::transport::{ConsoleTransport, ConsoleWriters, Sink, SinkConfig, SinkSettings};
 
 pub struct LoggingConfig {
-    module_name: &'static str,
     level: Level,
     get_level_name: fn (level: Level) -> &'static str,
+    align_level_names: bool,
+    messages: Option<Arc<dyn Messages>>,
+    clock: Option<Arc<dyn Clock>>,
+    highlight_values: bool,
+    indent_multiline: bool,
+    full_timestamps: bool,
+    metadata_style: Option<ansi_term::Style>,
+    console_encoder: Option<Box<dyn Encoder>>,
+    console_writers: Option<ConsoleWriters>,
+    environment: EnvironmentOverrides,
+    split_level: Level,
+    console_level: LevelFilter,
+    bell_level: Option<Level>,
+    files: Vec<FileSink>,
+    audit_files: Vec<FileSink>,
+    sinks: Vec<SinkConfig>,
+    pipeline: PipelineConfig,
+    flush_on_exit: bool,
+    attach: bool,
+    // Disabled when the config is attached to the existing logger which already writes to console
+    console: bool,
+    strict: bool,
+    // Misconfigurations found before the logger is built (reported only in strict mode)
+    errors: Vec<Error>,
+    import_context: bool,
+    static_fields: Vec<(String, String)>,
+    text_static_fields: bool,
+    source_location: SourceLocation,
+    time_zone: TimeZone,
+    console_time_zone: Option<TimeZone>,
+    state: Arc<State>,
 }
 
 impl LoggingConfig {
-    pub fn new(module_name: &'static str, level: Level) -> Self {
+    pub fn new<N: Into<Cow<'static, str>>>(module_name: N, level: Level) -> Self {
         LoggingConfig {
-            module_name, level,
-            get_level_name: |level| {
-                match level {
-                    Level::Error => "E: ",
-                    Level::Warn  => "W: ",
-                    Level::Info  => "I: ",
-                    Level::Debug => "D: ",
-                    Level::Trace => "T: ",
-                }
-            }
+            level,
+            get_level_name: encoder::get_default_level_name,
+            align_level_names: false,
+            messages: None,
+            clock: None,
+            highlight_values: false,
+            indent_multiline: false,
+            full_timestamps: false,
+            metadata_style: None,
+            console_encoder: None,
+            console_writers: None,
+            environment: EnvironmentOverrides::default(),
+            split_level: Level::Warn,
+            console_level: LevelFilter::Trace,
+            bell_level: None,
+            files: Vec::new(),
+            audit_files: Vec::new(),
+            sinks: Vec::new(),
+            pipeline: PipelineConfig::default(),
+            flush_on_exit: false,
+            attach: false,
+            console: true,
+            strict: false,
+            errors: Vec::new(),
+            import_context: false,
+            static_fields: Vec::new(),
+            text_static_fields: false,
+            source_location: SourceLocation::default(),
+            time_zone: TimeZone::Local,
+            console_time_zone: None,
+            state: Arc::new(State::new(module_name.into(), level)),
         }
     }
 
@@ -42,131 +219,607 @@ impl LoggingConfig {
         self
     }
 
+    /// Localizes level names and the messages generated by the crate itself. Applies to all
+    /// loggers since the messages aren't bound to a particular config (span messages for example).
+    /// They are installed when the logger is built.
+    pub fn messages<M: Messages + 'static>(mut self, messages: M) -> Self {
+        self.messages = Some(Arc::new(messages));
+        self.get_level_name = messages::get_level_name;
+        self
+    }
+
+    /// Sets the clock used for record timestamps and all measured intervals. Like the messages,
+    /// it applies to all loggers and is installed when the logger is built.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
     pub fn level_names(mut self, get: fn (level: Level) -> &'static str) -> Self {
         self.get_level_name = get;
         self
     }
 
-    pub fn dispatch(self) -> Dispatch {
-        let stdout_dispatcher =
-            self.configure_formatter(Dispatch::new(), atty::is(atty::Stream::Stdout))
-            .filter(|metadata| metadata.level() >= Level::Info)
-            .chain(io::stdout());
-
-        let stderr_dispatcher =
-            self.configure_formatter(Dispatch::new(), atty::is(atty::Stream::Stderr))
-            .filter(|metadata| metadata.level() < Level::Info)
-            .chain(io::stderr());
-
-        Dispatch::new()
-            .level(if self.level >= Level::Debug {
-                LevelFilter::Warn
-            } else {
-                LevelFilter::Off
-            })
-            .level_for(self.module_name, self.level.to_level_filter())
-            .chain(stdout_dispatcher)
-            .chain(stderr_dispatcher)
+    /// Pads level names of the text outputs to the widest one (see
+    /// [`TextEncoder::align_level_names()`]).
+    pub fn align_level_names(mut self) -> Self {
+        self.align_level_names = true;
+        self
     }
 
-    pub fn build(self) -> Result<(), SetLoggerError> {
-        self.dispatch().apply()
+    /// Highlights numbers, durations, byte sizes and quoted strings in messages when output is
+    /// colored.
+    pub fn highlight_values(mut self) -> Self {
+        self.highlight_values = true;
+        self
     }
 
-    fn configure_formatter(&self, dispatcher: Dispatch, colored_output: bool) -> Dispatch {
-        let max_level = self.level;
-        let get_level_name = self.get_level_name;
+    /// Indents continuation lines of multi-line messages (see [`debug_pretty!`]) to the message
+    /// start.
+    pub fn indent_multiline(mut self) -> Self {
+        self.indent_multiline = true;
+        self
+    }
 
-        if self.level < Level::Debug {
-            dispatcher.format(move |out, message, record| {
-                let level = record.level();
-                let level_name = get_level_name(level);
-                let context = GlobalContext::get(max_level);
-
-                if colored_output {
-                    let color = get_level_color(level);
-                    write_log(out, level, format_args!(
-                        "{color_prefix}{level_name}{context}{message}{color_suffix}",
-                        color_prefix=color.prefix(), color_suffix=color.suffix(),
-                    ));
-                } else {
-                    write_log(out, level, format_args!("{level_name}{context}{message}"));
+    /// Renders time and source location with the specified style (for example dim gray) instead of
+    /// the level color in colored output, so they don't compete with the message.
+    pub fn metadata_style(mut self, style: ansi_term::Style) -> Self {
+        self.metadata_style = Some(style);
+        self
+    }
+
+    /// Uses full RFC 3339 timestamps for file and custom sinks with the default encoder, which is
+    /// handy for long-running processes. Console output isn't affected.
+    pub fn full_timestamps(mut self) -> Self {
+        self.full_timestamps = true;
+        self
+    }
+
+    /// Sets the level for the specified target and its children. The most specific target wins.
+    pub fn level_for<T: Into<Cow<'static, str>>>(self, target: T, level: LevelFilter) -> Self {
+        let directive = Directive {
+            target: Some(target.into().into_owned()),
+            level,
+        };
+
+        self.state.levels.configure(|levels| {
+            levels.configured.retain(|existing| existing.target != directive.target);
+            levels.configured.push(directive);
+        });
+
+        self
+    }
+
+    /// Applies RUST_LOG-style directives (`target=level` or just `level` for all other targets)
+    /// separated by commas or newlines, which is handy for passing the levels via a command line
+    /// option or an environment variable. Invalid directive string is ignored.
+    pub fn directives(mut self, directives: &str) -> Self {
+        match directives::parse(directives) {
+            Ok(directives) => self.state.levels.configure(|levels| {
+                for directive in directives {
+                    levels.configured.retain(|existing| existing.target != directive.target);
+                    levels.configured.push(directive);
                 }
-            })
-        } else {
-            dispatcher.format(move |out, message, record| {
-                let time = chrono::Local::now().format("[%T%.3f]");
-                let level = record.level();
-                let level_name = get_level_name(level);
-                let context = GlobalContext::get(max_level);
-
-                let file = if let (Some(mut file), Some(line)) = (record.file(), record.line()) {
-                    let mut file_width = 10;
-                    let mut line_width = 3;
-                    let mut line_extra_width = line / 1000;
-
-                    while line_extra_width > 0 && file_width > 0 {
-                        line_width += 1;
-                        file_width -= 1;
-                        line_extra_width /= 10;
-                    }
-
-                    if file.starts_with("src/") {
-                        file = &file[4..];
-                    }
-
-                    if file.len() > file_width {
-                        file = &file[file.len() - file_width..]
-                    }
-
-                    format!(" [{file:>file_width$}:{line:0line_width$}]",
-                            file=file, file_width=file_width, line=line, line_width=line_width)
-                } else {
-                    String::new()
-                };
-
-                if colored_output {
-                    let color = get_level_color(level);
-                    write_log(out, level, format_args!(
-                        "{color_prefix}{time}{file} {level_name}{context}{message}{color_suffix}",
-                        color_prefix=color.prefix(), color_suffix=color.suffix()
-                    ));
-                } else {
-                    write_log(out, level, format_args!("{time}{file} {level_name}{context}{message}"));
+            }),
+            Err(err) => self.errors.push(Error::InvalidDirectives(err)),
+        }
+        self
+    }
+
+    /// Sets the level for records with empty or `unknown` target (emitted by generated code or FFI
+    /// shims for example) instead of the default one.
+    pub fn unknown_target_level(self, level: LevelFilter) -> Self {
+        self.state.levels.configure(|levels| levels.unknown_target_level = Some(level));
+        self
+    }
+
+    /// Prefixes records with empty or `unknown` target with `[unknown target]` tag, so their
+    /// origin can be tracked down.
+    pub fn tag_unknown_targets(mut self) -> Self {
+        self.pipeline.message.tag_unknown_targets = true;
+        self
+    }
+
+    /// Overrides the encoder used for console output.
+    pub fn console_encoder<E: Encoder + 'static>(mut self, encoder: E) -> Self {
+        self.console_encoder = Some(Box::new(encoder));
+        self
+    }
+
+    /// Chooses the console encoder by the value of the specified environment variable: `text`
+    /// (the default one), `json` or `logfmt`. The configured encoder is preserved if the variable
+    /// is not set or its value is unknown.
+    pub fn console_format_from_env(mut self, variable: &str) -> Self {
+        let Ok(value) = std::env::var(variable) else {
+            return self;
+        };
+
+        match value.as_str() {
+            "text" => self.console_encoder = None,
+            "json" => self.console_encoder = Some(Box::new(JsonEncoder::new())),
+            "logfmt" => self.console_encoder = Some(Box::new(LogfmtEncoder::new())),
+            _ => self.errors.push(Error::UnknownFormat {variable: variable.to_owned(), value}),
+        }
+
+        self
+    }
+
+    /// Sets the most verbose level which is written to stderr: records of this level and more
+    /// severe ones go to stderr and all others to stdout. Defaults to `Level::Warn`.
+    pub fn split_at(mut self, level: Level) -> Self {
+        self.split_level = level;
+        self
+    }
+
+    /// Writes only records of the specified level and more severe ones to the console (for example
+    /// to log Debug records only to a file).
+    pub fn console_level(mut self, level: Level) -> Self {
+        self.console_level = level.to_level_filter();
+        self
+    }
+
+    /// Replaces stdout and stderr with the specified writers preserving the split (see
+    /// [`LoggingConfig::split_at`]).
+    pub fn console_writers<O, E>(mut self, stdout: O, stderr: E, colored: bool) -> Self
+        where O: Write + Send + 'static, E: Write + Send + 'static
+    {
+        self.console_writers = Some(ConsoleWriters {
+            stdout: Box::new(stdout),
+            stderr: Box::new(stderr),
+            colored,
+        });
+        self
+    }
+
+    /// Overrides the environment sensing (TTY detection, `TERM`, `NO_COLOR`, etc.).
+    pub fn environment(mut self, overrides: EnvironmentOverrides) -> Self {
+        self.environment = overrides;
+        self
+    }
+
+    pub fn file(mut self, sink: FileSink) -> Self {
+        self.files.push(sink);
+        self
+    }
+
+    /// Adds a custom sink which writes records encoded by the encoder to the transport.
+    pub fn sink<T, E>(mut self, transport: T, encoder: E) -> Self
+        where T: Transport + 'static, E: Encoder + 'static
+    {
+        self.sinks.push(SinkConfig::new(Arc::new(transport), Some(Box::new(encoder))));
+        self
+    }
+
+    /// Writes audit records (see [`audit!`]) to the specified file instead of the regular sinks.
+    /// The file receives only audit records and ignores its level.
+    pub fn audit(mut self, sink: FileSink) -> Self {
+        self.audit_files.push(sink);
+        self
+    }
+
+    /// Adds a custom sink for audit records (syslog for example). See [`LoggingConfig::audit()`].
+    pub fn audit_sink<T, E>(mut self, transport: T, encoder: E) -> Self
+        where T: Transport + 'static, E: Encoder + 'static
+    {
+        let mut sink = SinkConfig::new(Arc::new(transport), Some(Box::new(encoder)));
+        sink.audit = true;
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Limits the number of records per second from the specified target and its children. Records
+    /// exceeding the limit are dropped for the rest of the second and reported with a single summary
+    /// record.
+    pub fn burst_limit(mut self, target_prefix: &str, max_per_second: u32) -> Self {
+        self.pipeline.burst_limits.push(BurstLimit::new(target_prefix, max_per_second));
+        self
+    }
+
+    /// Collects all emitted records into the capture to make assertions on them in tests.
+    pub fn capture(mut self, capture: &Capture) -> Self {
+        self.pipeline.captures.push(capture.clone());
+        self
+    }
+
+    /// Calls the callback for each emitted record of the specified level and more severe ones (to
+    /// send a desktop notification on errors for example).
+    ///
+    /// The records logged by the callback are written to the sinks, but don't trigger the hooks.
+    pub fn on_level<F>(mut self, level: Level, callback: F) -> Self
+        where F: Fn(&log::Record) + Send + Sync + 'static
+    {
+        self.pipeline.hooks.push(LevelHook::new(level, Box::new(callback)));
+        self
+    }
+
+    /// Truncates messages longer than the specified number of bytes, so a huge record can't flood
+    /// the sinks.
+    pub fn max_message_length(mut self, length: usize) -> Self {
+        self.pipeline.message.max_length = Some(length);
+        self
+    }
+
+    /// Rings the terminal bell on records of the specified level and more severe ones when stderr
+    /// is a terminal.
+    pub fn bell_on(mut self, level: Level) -> Self {
+        self.bell_level = Some(level);
+        self
+    }
+
+    /// Registers a best-effort handler which flushes all sinks on normal process exit (including
+    /// `std::process::exit()`) and on panics.
+    ///
+    /// The panic handler is invoked after the previously installed one. It skips the flushing if
+    /// the panic has occurred inside of the logger itself (when the sink locks may be held by the
+    /// panicking thread), and a panic inside of a sink during the flushing (double panic) aborts
+    /// the process.
+    pub fn flush_on_exit(mut self) -> Self {
+        self.flush_on_exit = true;
+        self
+    }
+
+    /// Makes [`LoggingConfig::build()`] tolerate the already installed global logger (for plugins
+    /// which can't control the initialization order): if it has been configured by this crate,
+    /// the sinks of this config are attached to it, otherwise logging via this config is disabled
+    /// with a warning.
+    ///
+    /// The console is already written by the existing logger, so only the other sinks (files,
+    /// custom ones) are attached to not duplicate the console output. Records get to the console
+    /// only if they are enabled by the existing logger.
+    pub fn attach_to_existing(mut self) -> Self {
+        self.attach = true;
+        self
+    }
+
+    /// Attaches constant fields to every record in structured outputs (and text ones with
+    /// [`LoggingConfig::text_static_fields()`]). A value in `$NAME` form is
+    /// taken from the `NAME` environment variable (the field is omitted if it's not set).
+    ///
+    /// Fields named like the ones written by the JSON and logfmt encoders themselves (`level`,
+    /// `message`, etc.) are prefixed with `_` in their output.
+    pub fn static_fields(mut self, fields: &[(&str, &str)]) -> Self {
+        for &(name, value) in fields {
+            let value = match value.strip_prefix('$') {
+                Some(variable) => match std::env::var(variable) {
+                    Ok(value) => value,
+                    Err(_) => {
+                        self.errors.push(Error::MissingVariable {
+                            field: name.to_owned(),
+                            variable: variable.to_owned(),
+                        });
+                        continue;
+                    },
+                },
+                None => value.to_owned(),
+            };
+            self.static_fields.push((name.to_owned(), value));
+        }
+        self
+    }
+
+    /// Prefixes messages of the text outputs with the static fields (`[service=billing env=prod] `),
+    /// which are written only to structured outputs by default.
+    pub fn text_static_fields(mut self) -> Self {
+        self.text_static_fields = true;
+        self
+    }
+
+    /// Makes [`LoggingConfig::build()`] fail on misconfigurations which are tolerated by default:
+    /// unopenable log files, invalid directives, unknown console format, unset environment
+    /// variables of static fields, malformed inherited context and inability to attach to the
+    /// existing logger. Doesn't affect
+    /// [`LoggingConfig::into_parts()`] and [`LoggingConfig::logger()`].
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Imports the context and the static fields exported by the parent process via
+    /// [`Handle::export_context()`]. The inherited context precedes [`GlobalContext`] in messages
+    /// and the explicitly specified static fields take precedence over the inherited ones.
+    pub fn import_context(mut self) -> Self {
+        self.import_context = true;
+        self
+    }
+
+    /// Sets how source locations are rendered by all sinks. File and line are used by default.
+    ///
+    /// Please note that `log` macros embed source file paths into the binary regardless of this
+    /// option (use `--remap-path-prefix` rustc option to hide them).
+    pub fn source_location(mut self, location: SourceLocation) -> Self {
+        self.source_location = location.resolve();
+        self
+    }
+
+    /// Sets the default time zone of the timestamps for all sinks. Local time is used by default.
+    pub fn time_zone(mut self, time_zone: TimeZone) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    /// Overrides the time zone of the console timestamps (so the console can use local time while
+    /// files use UTC for example).
+    pub fn console_time_zone(mut self, time_zone: TimeZone) -> Self {
+        self.console_time_zone = Some(time_zone);
+        self
+    }
+
+    pub fn handle(&self) -> Handle {
+        Handle::new(self.state.clone())
+    }
+
+    pub fn dispatch(self) -> Dispatch {
+        self.into_parts().dispatch()
+    }
+
+    /// Disassembles the config into the logger parts which can be recomposed with custom sinks
+    /// without losing the configured formatting and runtime level filtering.
+    pub fn into_parts(mut self) -> LoggerParts {
+        let prepared = self.prepare();
+        self.report_errors();
+
+        let (parts, global_settings) = self.assemble(prepared);
+        global_settings.apply();
+        parts
+    }
+
+    // Opens the files and imports the inherited context recording all misconfigurations
+    fn prepare(&mut self) -> Prepared {
+        let mut files = Vec::new();
+        let regular_files = std::mem::take(&mut self.files).into_iter().map(|sink| (sink, false));
+        let audit_files = std::mem::take(&mut self.audit_files).into_iter().map(|sink| (sink, true));
+
+        for (sink, audit) in regular_files.chain(audit_files) {
+            let (path, per_thread) = (sink.path().clone(), sink.is_per_thread());
+
+            match sink.open() {
+                Ok(mut sink) => {
+                    self.state.files.lock().unwrap().push(LogFile {path, per_thread});
+                    sink.audit = audit;
+                    files.push(sink);
+                },
+                Err(error) => self.errors.push(Error::OpenFile {path, error}),
+            }
+        }
+
+        let inherited_context = self.import_context.then(|| {
+            let inherited = propagate::import();
+            self.errors.extend(inherited.invalid.iter().cloned().map(Error::InvalidContext));
+            inherited
+        });
+
+        Prepared {files, inherited_context}
+    }
+
+    // Reports the tolerated misconfigurations (only unopenable files aren't silent)
+    fn report_errors(&mut self) {
+        for error in self.errors.drain(..) {
+            if let Error::OpenFile {..} = error {
+                let _ = writeln!(io::stderr(), "{error}.");
+            }
+        }
+    }
+
+    fn assemble(mut self, prepared: Prepared) -> (LoggerParts, GlobalSettings) {
+        let console_encoder = match self.console_encoder.take() {
+            Some(encoder) => encoder,
+            None => Box::new(self.text_encoder(self.level >= Level::Debug, false)),
+        };
+
+        let environment = self.environment.resolve();
+        if let (Some(level), true) = (self.bell_level, environment.stderr_tty) {
+            self.pipeline.hooks.push(LevelHook::new(level, Box::new(hook::ring_bell)));
+        }
+
+        let mut sinks = Vec::new();
+        if self.console {
+            let console = ConsoleTransport::new(self.console_writers.take(), self.split_level, &environment);
+            let mut console = SinkConfig::new(Arc::new(console), Some(console_encoder));
+            console.time_zone = self.console_time_zone;
+            console.level = self.console_level;
+            console.console = true;
+            sinks.push(console);
+        }
+
+        sinks.extend(prepared.files);
+        sinks.append(&mut self.sinks);
+
+        let mut global_settings = GlobalSettings {
+            messages: self.messages.take(),
+            clock: self.clock.take(),
+            inherited_context: None,
+            flush_on_exit: self.flush_on_exit,
+            state: self.state.clone(),
+        };
+
+        if let Some(inherited) = prepared.inherited_context {
+            global_settings.inherited_context = Some(inherited.names);
+
+            for (name, value) in inherited.fields {
+                if !self.static_fields.iter().any(|(existing, _)| *existing == name) {
+                    self.static_fields.push((name, value));
                 }
-            })
+            }
+        }
+        self.state.static_fields.lock().unwrap().clone_from(&self.static_fields);
+
+        let settings = Arc::new(SinkSettings {
+            max_level: self.level,
+            static_fields: std::mem::take(&mut self.static_fields),
+            source_location: self.source_location,
+            time_zone: self.time_zone,
+            state: self.state.clone(),
+        });
+
+        let (mut regular_sinks, mut sinks_by_role) = (Vec::new(), SinksByRole::default());
+
+        for mut sink in sinks {
+            self.state.transports.lock().unwrap().push(sink.transport.clone());
+            let audit = sink.audit;
+            let error_context = sink.error_context.is_some() && !audit;
+            if let Some(ref buffer) = sink.error_context {
+                self.state.error_contexts.lock().unwrap().push(buffer.clone());
+            }
+            if error_context {
+                let level = sink.level;
+                self.state.levels.configure(|levels| {
+                    levels.context_level = levels.context_level.max(Some(level));
+                });
+            }
+
+            let encoder = sink.encoder.take().unwrap_or_else(|| {
+                Box::new(self.text_encoder(true, self.full_timestamps || audit))
+            });
+            let sink = Arc::new(Sink::new(sink, encoder, settings.clone()));
+            if sink.heartbeat().is_some() {
+                heartbeat::start(Arc::downgrade(&sink));
+            }
+
+            let sink = Box::new(sink) as Box<dyn log::Log>;
+            if audit {
+                sinks_by_role.audit.push(sink);
+            } else if error_context {
+                sinks_by_role.error_context.push(sink);
+            } else {
+                regular_sinks.push(sink);
+            }
+        }
+
+        let parts = LoggerParts::new(
+            self.text_encoder(true, self.full_timestamps), Filter::new(self.state.clone()),
+            regular_sinks, sinks_by_role, self.pipeline);
+
+        (parts, global_settings)
+    }
+
+    /// Returns the configured logger without installing it as the global one. Its
+    /// [`Handle`] doesn't change the global max level then (see [`EasyLogger`]).
+    pub fn logger(self) -> EasyLogger {
+        self.state.levels.disown_max_level();
+        assemble_logger(self.into_parts())
+    }
+
+    pub fn build(mut self) -> Result<(), Error> {
+        let (attach, strict) = (self.attach, self.strict);
+        if attach && global::is_configured() {
+            self.console = false;
+        }
+
+        let prepared = self.prepare();
+        if let (true, Some(error)) = (strict, self.errors.drain(..).next()) {
+            return Err(error);
+        }
+        self.report_errors();
+
+        let (parts, global_settings) = self.assemble(prepared);
+        global::install(assemble_logger(parts), attach, strict, || global_settings.apply())
+    }
+
+    fn text_encoder(&self, detailed: bool, full_timestamps: bool) -> TextEncoder {
+        let mut encoder = TextEncoder::new().level_names(self.get_level_name);
+        if self.align_level_names {
+            encoder = encoder.align_level_names();
+        }
+        if !detailed {
+            encoder = encoder.compact();
         }
+        if full_timestamps {
+            encoder = encoder.full_timestamps();
+        }
+        if self.highlight_values {
+            encoder = encoder.highlight_values();
+        }
+        if self.indent_multiline {
+            encoder = encoder.indent_multiline();
+        }
+        if let Some(style) = self.metadata_style {
+            encoder = encoder.metadata_style(style);
+        }
+        if self.text_static_fields {
+            encoder = encoder.static_fields();
+        }
+        encoder
     }
 }
 
-pub fn init(module_name: &'static str, level: Level) -> Result<(), SetLoggerError> {
-    LoggingConfig::new(module_name, level).build()
+// The config parts whose setup may fail, so they are prepared before the logger is assembled
+struct Prepared {
+    files: Vec<SinkConfig>,
+    inherited_context: Option<propagate::Context>,
 }
 
-fn get_level_color(level: Level) -> Color {
-    match level {
-        Level::Error => Color::Red,
-        Level::Warn  => Color::Yellow,
-        Level::Info  => Color::Green,
-        Level::Debug => Color::Cyan,
-        Level::Trace => Color::Purple,
-    }
+// The settings which affect the whole process, so they are applied only after the logger is
+// installed (or returned to the caller) to not leave them changed if the installation fails
+struct GlobalSettings {
+    messages: Option<Arc<dyn Messages>>,
+    clock: Option<Arc<dyn Clock>>,
+    inherited_context: Option<Vec<String>>,
+    flush_on_exit: bool,
+    state: Arc<State>,
 }
 
-fn write_log(out: FormatCallback, level: Level, formatted_message: fmt::Arguments) {
-    lazy_static! {
-        static ref OUTPUT_MUTEX: Mutex<()> = Mutex::new(());
+impl GlobalSettings {
+    fn apply(self) {
+        if let Some(messages) = self.messages {
+            messages::set(messages);
+        }
+        if let Some(clock) = self.clock {
+            clock::set(clock);
+        }
+        if let Some(names) = self.inherited_context {
+            context::GlobalContext::set_inherited(names);
+        }
+
+        fork::register(self.state.clone());
+        if self.flush_on_exit {
+            exit::flush_on_exit(self.state);
+        }
     }
+}
 
-    // Since we write into stdout and stderr we should guard any write with a mutex to not get the
-    // output interleaved.
-    let _lock = OUTPUT_MUTEX.lock();
+fn assemble_logger(parts: LoggerParts) -> EasyLogger {
+    let filter = parts.filter.clone();
+    let (_, logger) = parts.dispatch().into_log();
+    EasyLogger::new(logger, filter)
+}
 
-    out.finish(formatted_message);
+pub fn init<N: Into<Cow<'static, str>>>(module_name: N, level: Level) -> Result<(), Error> {
+    LoggingConfig::new(module_name, level).build()
+}
 
-    let _ = if level >= Level::Info {
-        io::stdout().flush()
-    } else {
-        io::stderr().flush()
-    };
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use log::{Log, Metadata};
+
+    use super::*;
+
+    #[test]
+    fn directives() {
+        let logger = LoggingConfig::new("app", Level::Info)
+            .directives("hyper=debug, tokio=loud")
+            .directives("warn, app::db=trace")
+            .logger();
+
+        for (target, level, enabled) in [
+            ("app", Level::Info, true),
+            ("app::db", Level::Trace, true),
+            ("hyper", Level::Debug, false),
+            ("other", Level::Warn, true),
+            ("other", Level::Info, false),
+        ] {
+            let metadata = Metadata::builder().target(target).level(level).build();
+            assert_eq!(logger.enabled(&metadata), enabled, "{target} {level}");
+        }
+    }
+
+    #[test]
+    fn strict() {
+        let result = LoggingConfig::new("app", Level::Info).directives("hyper=loud").strict().build();
+        assert!(matches!(result, Err(Error::InvalidDirectives(_))), "{result:?}");
+
+        std::env::set_var("EASY_LOGGING_TEST_FORMAT", "yaml");
+        let result = LoggingConfig::new("app", Level::Info)
+            .console_format_from_env("EASY_LOGGING_TEST_FORMAT").strict().build();
+        assert!(matches!(result, Err(Error::UnknownFormat {ref value, ..}) if value == "yaml"), "{result:?}");
+    }
+}
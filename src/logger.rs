@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::handle::State;
+use crate::parts::Filter;
+
+/// The logger configured by [`LoggingConfig`](crate::LoggingConfig) which isn't installed as the
+/// global one, so it can be composed with other loggers or installed manually.
+///
+/// Please note that level checks are performed by the logger itself, so the global max level (see
+/// [`EasyLogger::max_level()`]) should be set by the application when it installs the logger. The
+/// logger never changes it on its own, including level changes via [`Handle`](crate::Handle).
+pub struct EasyLogger {
+    logger: Box<dyn Log>,
+    filter: Filter,
+}
+
+impl EasyLogger {
+    pub(crate) fn new(logger: Box<dyn Log>, filter: Filter) -> EasyLogger {
+        EasyLogger {logger, filter}
+    }
+
+    /// The most verbose level which is currently enabled for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.filter.max_level()
+    }
+
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    pub(crate) fn state(&self) -> Arc<State> {
+        self.filter.state().clone()
+    }
+
+    pub(crate) fn module_name(&self) -> String {
+        self.filter.module_name()
+    }
+}
+
+impl Log for EasyLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.logger.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.logger.log(record)
+    }
+
+    fn flush(&self) {
+        self.logger.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use crate::LoggingConfig;
+
+    use super::*;
+
+    #[test]
+    fn global_max_level() {
+        let config = LoggingConfig::new("test", Level::Info);
+        let handle = config.handle();
+        let logger = config.logger();
+
+        log::set_max_level(LevelFilter::Trace);
+
+        handle.silence();
+        assert_eq!(logger.max_level(), LevelFilter::Off);
+        handle.unsilence();
+        handle.set_level(Level::Warn);
+        handle.execute("level other=error").unwrap();
+
+        assert_eq!(logger.max_level(), LevelFilter::Warn);
+        assert_eq!(log::max_level(), LevelFilter::Trace);
+    }
+}
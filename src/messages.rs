@@ -0,0 +1,102 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use log::Level;
+
+use crate::fmt;
+
+/// Provides level names and the messages generated by the crate itself, so they can be localized.
+///
+/// All methods default to the built-in English variants.
+pub trait Messages: Send + Sync {
+    fn level_name(&self, level: Level) -> &'static str {
+        crate::encoder::get_default_level_name(level)
+    }
+
+    /// Summary of the records dropped due to [burst limit](crate::LoggingConfig::burst_limit).
+    fn records_suppressed(&self, count: u64, target: &str, max_per_second: u32) -> String {
+        format!("{count} log records from {target:?} have been suppressed due to exceeding the limit of {max_per_second} records per second.")
+    }
+
+    /// Summary of the records dropped from the [early records](crate::capture_early_records)
+    /// buffer.
+    fn early_records_dropped(&self, count: usize) -> String {
+        format!("{count} log records emitted before logger initialization have been dropped.")
+    }
+
+    /// [Heartbeat](crate::FileSink::heartbeat) record.
+    fn heartbeat(&self, idle_time: Duration, pid: u32) -> String {
+        format!("(no log output for {}, still alive, pid {pid})", fmt::duration(idle_time))
+    }
+
+    /// Debug record written before an error by [`FileSink::error_context()`](crate::FileSink::error_context).
+    fn preceding_context(&self, message: &str) -> String {
+        format!("preceding context: {message}")
+    }
+
+    /// Marker appended to a message whose `Display` implementation has failed or panicked.
+    fn formatting_failed(&self) -> String {
+        "<unable to format the message>".to_owned()
+    }
+
+    /// Suffix of a message truncated due to
+    /// [`LoggingConfig::max_message_length()`](crate::LoggingConfig::max_message_length).
+    fn message_truncated(&self, size: usize) -> String {
+        format!("… ({} truncated)", fmt::bytes(size as u64))
+    }
+
+    /// Progress record of [`ProgressLogger`](crate::ProgressLogger). ETA is unknown until the
+    /// first item is processed and omitted on completion.
+    fn progress(&self, processed: u64, total: u64, eta: Option<Duration>) -> String {
+        let percent = match total {
+            0 => 100,
+            total => (u128::from(processed) * 100 / u128::from(total)) as u64,
+        };
+
+        let mut message = format!("processed {}/{} ({percent}%)", fmt::count(processed), fmt::count(total));
+        if let Some(eta) = eta {
+            message.push_str(&format!(", ETA {}", fmt::duration(eta)));
+        }
+        message
+    }
+
+    /// Prefix of the records with unknown target (see
+    /// [`LoggingConfig::tag_unknown_targets()`](crate::LoggingConfig::tag_unknown_targets)).
+    fn unknown_target_tag(&self) -> String {
+        "[unknown target] ".to_owned()
+    }
+
+    fn span_started(&self, name: &str) -> String {
+        format!("{name} started.")
+    }
+
+    fn span_finished(&self, name: &str, duration: Duration) -> String {
+        format!("{name} finished in {}.", fmt::duration(duration))
+    }
+
+    fn span_failed(&self, name: &str, duration: Duration, error: &str) -> String {
+        format!("{name} failed in {}: {error}", fmt::duration(duration))
+    }
+}
+
+struct DefaultMessages;
+
+impl Messages for DefaultMessages {
+}
+
+lazy_static! {
+    static ref MESSAGES: RwLock<Arc<dyn Messages>> = RwLock::new(Arc::new(DefaultMessages));
+}
+
+pub(crate) fn get() -> Arc<dyn Messages> {
+    MESSAGES.read().unwrap().clone()
+}
+
+pub(crate) fn set(messages: Arc<dyn Messages>) {
+    *MESSAGES.write().unwrap() = messages;
+}
+
+pub(crate) fn get_level_name(level: Level) -> &'static str {
+    MESSAGES.read().unwrap().level_name(level)
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use fern::Dispatch;
+use log::{LevelFilter, Log, Metadata};
+
+use crate::audit;
+use crate::encoder::TextEncoder;
+use crate::handle::State;
+use crate::pipeline::{Pipeline, PipelineConfig};
+
+/// The pieces the logger is assembled from, returned by
+/// [`LoggingConfig::into_parts()`](crate::LoggingConfig::into_parts) for custom composition.
+pub struct LoggerParts {
+    /// Text encoder configured according to the logging config
+    pub encoder: TextEncoder,
+    /// Runtime level filter controlled by [`Handle`](crate::Handle)
+    pub filter: Filter,
+    /// The configured sinks (except the audit and error context ones). Each of them filters
+    /// records by its own level on its own.
+    pub sinks: Vec<Box<dyn Log>>,
+    sinks_by_role: SinksByRole,
+    pipeline: PipelineConfig,
+}
+
+// Sinks which receive records the regular ones don't
+#[derive(Default)]
+pub(crate) struct SinksByRole {
+    pub audit: Vec<Box<dyn Log>>,
+    // Receive the records enabled only for error context capturing as well
+    pub error_context: Vec<Box<dyn Log>>,
+}
+
+impl LoggerParts {
+    pub(crate) fn new(
+        encoder: TextEncoder, filter: Filter, sinks: Vec<Box<dyn Log>>, sinks_by_role: SinksByRole,
+        pipeline: PipelineConfig,
+    ) -> LoggerParts {
+        LoggerParts {encoder, filter, sinks, sinks_by_role, pipeline}
+    }
+
+    /// Assembles the parts back into a dispatcher the same way
+    /// [`LoggingConfig::dispatch()`](crate::LoggingConfig::dispatch) does (burst limits
+    /// are applied to all sinks including the added ones).
+    pub fn dispatch(self) -> Dispatch {
+        let filter = self.filter;
+        let pipeline: Box<dyn Log> = Box::new(Pipeline::new(
+            self.sinks, self.sinks_by_role, self.pipeline, filter.state.clone()));
+
+        Dispatch::new()
+            .level(LevelFilter::Trace)
+            .filter(move |metadata| filter.enabled(metadata))
+            .chain(pipeline)
+    }
+}
+
+/// Runtime per-target level filter which is shared with the logger and its
+/// [`Handle`](crate::Handle).
+#[derive(Clone)]
+pub struct Filter {
+    state: Arc<State>,
+}
+
+impl Filter {
+    pub(crate) fn new(state: Arc<State>) -> Filter {
+        Filter {state}
+    }
+
+    /// Audit records are always enabled.
+    pub fn enabled(&self, metadata: &Metadata) -> bool {
+        audit::is_audit(metadata.target()) || self.state.levels.enabled(metadata)
+    }
+
+    /// The most verbose level which is enabled for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.state.levels.max_level()
+    }
+
+    #[cfg_attr(not(feature = "bundle"), allow(dead_code))]
+    pub(crate) fn state(&self) -> &Arc<State> {
+        &self.state
+    }
+
+    pub(crate) fn module_name(&self) -> String {
+        self.state.levels.module_name()
+    }
+}
@@ -0,0 +1,354 @@
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Weak};
+use std::thread;
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::audit;
+use crate::burst::{self, BurstLimit};
+use crate::capture::Capture;
+use crate::clock;
+use crate::exit::LoggerScope;
+use crate::handle::State;
+use crate::parts::SinksByRole;
+use crate::hook::LevelHook;
+use crate::messages;
+use crate::handle::{is_target_of, is_unknown_target};
+
+// Configuration of the logic which is common for all sinks
+#[derive(Default)]
+pub(crate) struct PipelineConfig {
+    pub burst_limits: Vec<BurstLimit>,
+    pub captures: Vec<Capture>,
+    pub hooks: Vec<LevelHook>,
+    pub message: MessageOptions,
+}
+
+// Processing of the messages which is common for all sinks
+#[derive(Clone, Copy, Default)]
+pub(crate) struct MessageOptions {
+    pub max_length: Option<usize>,
+    pub tag_unknown_targets: bool,
+}
+
+impl MessageOptions {
+    // Formats the message once for all sinks, so a broken Display implementation can't break the
+    // logger
+    pub fn format(&self, target: &str, args: fmt::Arguments) -> String {
+        let mut message = format_safely(args);
+
+        if let Some(max_length) = self.max_length {
+            if message.len() > max_length {
+                let mut length = max_length;
+                while !message.is_char_boundary(length) {
+                    length -= 1;
+                }
+
+                let truncated = message.len() - length;
+                message.truncate(length);
+                message.push_str(&messages::get().message_truncated(truncated));
+            }
+        }
+
+        if self.tag_unknown_targets && is_unknown_target(target) {
+            message = format!("{}{message}", messages::get().unknown_target_tag());
+        }
+
+        message
+    }
+}
+
+// Applies the logic which is common for all sinks and passes the records to them
+pub(crate) struct Pipeline {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    sinks: Vec<Box<dyn Log>>,
+    audit_sinks: Vec<Box<dyn Log>>,
+    context_sinks: Vec<Box<dyn Log>>,
+    burst_limits: Vec<BurstLimit>,
+    captures: Vec<Capture>,
+    hooks: Vec<LevelHook>,
+    message: MessageOptions,
+    state: Arc<State>,
+}
+
+impl Pipeline {
+    pub fn new(
+        sinks: Vec<Box<dyn Log>>, sinks_by_role: SinksByRole, config: PipelineConfig, state: Arc<State>,
+    ) -> Pipeline {
+        let inner = Arc::new(Inner {
+            sinks, state,
+            audit_sinks: sinks_by_role.audit,
+            context_sinks: sinks_by_role.error_context,
+            burst_limits: config.burst_limits,
+            captures: config.captures,
+            hooks: config.hooks,
+            message: config.message,
+        });
+
+        if !inner.burst_limits.is_empty() {
+            start_burst_timer(Arc::downgrade(&inner));
+        }
+
+        Pipeline {inner}
+    }
+}
+
+// Reports the suppressed records even if the noisy target goes quiet and no more records arrive
+// to rotate the burst limit windows
+fn start_burst_timer(pipeline: Weak<Inner>) {
+    thread::spawn(move || loop {
+        thread::sleep(burst::WINDOW);
+
+        let Some(pipeline) = pipeline.upgrade() else {
+            return;
+        };
+
+        let _scope = LoggerScope::enter();
+        pipeline.report_suppressed(|limit| limit.rotate(clock::monotonic()));
+    });
+}
+
+impl Inner {
+    fn check_burst_limits(&self, record: &Record) -> bool {
+        if self.burst_limits.is_empty() {
+            return true;
+        }
+
+        let now = clock::monotonic();
+        self.report_suppressed(|limit| limit.rotate(now));
+
+        self.get_burst_limit(record.target()).is_none_or(|limit| limit.allow())
+    }
+
+    fn report_suppressed<F: Fn(&BurstLimit) -> Option<u64>>(&self, get_dropped: F) {
+        for limit in &self.burst_limits {
+            if let Some(dropped) = get_dropped(limit) {
+                let message = messages::get().records_suppressed(
+                    dropped, &limit.target_prefix, limit.max_per_second);
+
+                self.emit(&Record::builder()
+                    .level(Level::Warn)
+                    .target(&limit.target_prefix)
+                    .args(format_args!("{message}"))
+                    .build(), false);
+            }
+        }
+    }
+
+    fn get_burst_limit(&self, target: &str) -> Option<&BurstLimit> {
+        self.burst_limits.iter()
+            .filter(|limit| is_target_of(target, &limit.target_prefix))
+            .max_by_key(|limit| limit.target_prefix.len())
+    }
+
+    // Checks whether the record is going to reach only error context sinks
+    fn is_context_only(&self, metadata: &Metadata) -> bool {
+        !self.context_sinks.is_empty() && metadata.level() >= Level::Debug && !audit::is_audit(metadata.target()) &&
+            self.state.levels.context_only(metadata)
+    }
+
+    fn emit(&self, record: &Record, context_only: bool) {
+        // The records buffered by error context sinks are emitted only as a context of an error
+        if !context_only {
+            for capture in &self.captures {
+                capture.push(record);
+            }
+        }
+
+        if audit::is_audit(record.target()) && !self.audit_sinks.is_empty() {
+            // Audit sinks don't filter records by level
+            for sink in &self.audit_sinks {
+                sink.log(record);
+            }
+        } else {
+            let sinks = if context_only { &[] } else { self.sinks.as_slice() };
+            for sink in sinks.iter().chain(&self.context_sinks) {
+                if sink.enabled(record.metadata()) {
+                    sink.log(record);
+                }
+            }
+        }
+
+        for hook in &self.hooks {
+            hook.call(record);
+        }
+    }
+}
+
+impl Log for Pipeline {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+impl Log for Inner {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        if audit::is_audit(metadata.target()) {
+            return true;
+        }
+
+        (!self.captures.is_empty() || self.sinks.iter().chain(&self.context_sinks).any(|sink| sink.enabled(metadata))) &&
+            !self.get_burst_limit(metadata.target()).is_some_and(|limit| limit.is_exhausted(clock::monotonic()))
+    }
+
+    fn log(&self, record: &Record) {
+        let _scope = LoggerScope::enter();
+
+        // Audit records are never suppressed
+        if !audit::is_audit(record.target()) && !self.check_burst_limits(record) {
+            return;
+        }
+
+        let context_only = self.is_context_only(record.metadata());
+        if !context_only {
+            self.state.stats.count(record.level());
+        }
+
+        let message = self.message.format(record.target(), *record.args());
+
+        self.emit(&Record::builder()
+            .metadata(record.metadata().clone())
+            .module_path(record.module_path())
+            .file(record.file())
+            .line(record.line())
+            .key_values(record.key_values())
+            .args(format_args!("{message}"))
+            .build(), context_only);
+    }
+
+    fn flush(&self) {
+        let _scope = LoggerScope::enter();
+
+        // Don't wait for the end of the window to not lose the summary on exit
+        self.report_suppressed(BurstLimit::take_dropped);
+
+        for sink in self.sinks.iter().chain(&self.audit_sinks).chain(&self.context_sinks) {
+            sink.flush();
+        }
+    }
+}
+
+// Formats the arguments tolerating Display implementations which fail or panic. NUL characters are
+// escaped since they break many log viewers and C APIs.
+pub(crate) fn format_safely(args: fmt::Arguments) -> String {
+    let mut message = String::new();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        fmt::write(&mut message, args)
+    }));
+
+    if !matches!(result, Ok(Ok(()))) {
+        if !message.is_empty() {
+            message.push(' ');
+        }
+        message.push_str(&messages::get().formatting_failed());
+    }
+
+    if message.contains('\0') {
+        message = message.replace('\0', "\\0");
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::clock::tests::freeze;
+
+    use super::*;
+
+    struct Failing;
+
+    impl fmt::Display for Failing {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "partial")?;
+            Err(fmt::Error)
+        }
+    }
+
+    #[test]
+    fn defensive_formatting() {
+        let failed = messages::get().formatting_failed();
+
+        assert_eq!(format_safely(format_args!("a\0b")), "a\\0b");
+        assert_eq!(format_safely(format_args!("{}", Failing)), format!("partial {failed}"));
+    }
+
+    #[test]
+    fn message_options() {
+        let options = MessageOptions {max_length: Some(5), tag_unknown_targets: true};
+        let (truncated, tag) = (messages::get().message_truncated(2), messages::get().unknown_target_tag());
+
+        assert_eq!(options.format("test", format_args!("аб")), "аб");
+        assert_eq!(options.format("test", format_args!("абв")), format!("аб{truncated}"));
+        assert_eq!(options.format("", format_args!("абв")), format!("{tag}аб{truncated}"));
+    }
+
+    #[test]
+    fn burst_summary() {
+        let clock = freeze();
+        let capture = Capture::new();
+
+        let pipeline = Pipeline::new(Vec::new(), SinksByRole::default(), PipelineConfig {
+            burst_limits: vec![BurstLimit::new("noisy", 2)],
+            captures: vec![capture.clone()],
+            ..Default::default()
+        }, Arc::new(State::new(Cow::Borrowed("test"), Level::Info)));
+
+        let log = |target: &str| pipeline.log(&Record::builder()
+            .level(Level::Info).target(target).args(format_args!("message")).build());
+
+        for _ in 0..5 {
+            log("noisy::module");
+        }
+        log("other");
+        assert!(!pipeline.enabled(&Metadata::builder().level(Level::Info).target("noisy").build()));
+
+        let summary = messages::get().records_suppressed(3, "noisy", 2);
+        capture.find(Level::Info).with_target("noisy::module").assert_count(2);
+        capture.find(Level::Info).with_target("other").assert_count(1);
+        capture.find(Level::Warn).with_message(&summary).assert_count(0);
+
+        clock.advance(burst::WINDOW);
+        log("noisy");
+        capture.find(Level::Warn).with_target("noisy").with_message(&summary).assert_count(1);
+        capture.find(Level::Info).with_target("noisy").assert_count(3);
+
+        assert_eq!(pipeline.inner.state.stats.to_string(), "error=0 warn=0 info=4 debug=0 trace=0");
+    }
+
+    #[test]
+    fn burst_summary_on_flush() {
+        let _clock = freeze();
+        let capture = Capture::new();
+
+        let pipeline = Pipeline::new(Vec::new(), SinksByRole::default(), PipelineConfig {
+            burst_limits: vec![BurstLimit::new("noisy", 1)],
+            captures: vec![capture.clone()],
+            ..Default::default()
+        }, Arc::new(State::new(Cow::Borrowed("test"), Level::Info)));
+
+        for _ in 0..3 {
+            pipeline.log(&Record::builder().level(Level::Info).target("noisy").args(format_args!("message")).build());
+        }
+        pipeline.flush();
+        pipeline.flush();
+
+        let summary = messages::get().records_suppressed(2, "noisy", 1);
+        capture.find(Level::Warn).with_message(&summary).assert_count(1);
+    }
+}
@@ -0,0 +1,172 @@
+use std::borrow::Cow;
+use std::panic::Location;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use log::{Level, Record};
+
+use crate::clock;
+use crate::global;
+use crate::messages;
+
+/// Logs progress of a long operation ("processed 12,000/80,000 (15%), ETA 4m 10s") at most once
+/// per the specified interval and on its completion.
+///
+/// Can be shared between threads processing the items in parallel.
+pub struct ProgressLogger {
+    total: u64,
+    interval: Duration,
+    level: Level,
+    target: Option<Cow<'static, str>>,
+    location: &'static Location<'static>,
+    // Monotonic time
+    start_time: Duration,
+    processed: AtomicU64,
+    last_log_time: Mutex<Duration>,
+}
+
+impl ProgressLogger {
+    /// Creates a logger for the operation over `total` items. The records are attributed to the
+    /// caller's source location.
+    #[track_caller]
+    pub fn new(total: u64, interval: Duration) -> ProgressLogger {
+        let now = clock::monotonic();
+
+        ProgressLogger {
+            total, interval,
+            level: Level::Info,
+            target: None,
+            location: Location::caller(),
+            start_time: now,
+            processed: AtomicU64::new(0),
+            last_log_time: Mutex::new(now),
+        }
+    }
+
+    /// Sets the level of the records. Defaults to `Level::Info`.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the target of the records. Defaults to the module name the logger is configured with.
+    pub fn target<T: Into<Cow<'static, str>>>(mut self, target: T) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Marks the specified number of items as processed. Completion of the operation is always
+    /// logged regardless of the interval.
+    pub fn add(&self, count: u64) {
+        if let Some((processed, now)) = self.update(count) {
+            self.log(processed, now);
+        }
+    }
+
+    // Returns the progress to log if it's time to
+    fn update(&self, count: u64) -> Option<(u64, Duration)> {
+        let previous = self.processed.fetch_add(count, Ordering::Relaxed);
+        let processed = previous.saturating_add(count);
+        let completed = previous < self.total && processed >= self.total;
+
+        let now = clock::monotonic();
+        let mut last_log_time = if completed {
+            self.last_log_time.lock().unwrap()
+        } else {
+            let Ok(last_log_time) = self.last_log_time.try_lock() else {
+                // Another thread is logging the progress right now
+                return None;
+            };
+
+            if now.saturating_sub(*last_log_time) < self.interval {
+                return None;
+            }
+            last_log_time
+        };
+        *last_log_time = now;
+
+        Some((processed, now))
+    }
+
+    fn log(&self, processed: u64, now: Duration) {
+        let target = match self.target {
+            Some(ref target) => target.clone(),
+            None => global::module_name().map_or(Cow::Borrowed(module_path!()), Cow::Owned),
+        };
+
+        if !crate::enabled(self.level, &target) {
+            return;
+        }
+
+        let message = self.message(processed, now);
+
+        log::logger().log(&Record::builder()
+            .level(self.level)
+            .target(&target)
+            .file(Some(self.location.file()))
+            .line(Some(self.location.line()))
+            .args(format_args!("{message}"))
+            .build());
+    }
+
+    fn message(&self, processed: u64, now: Duration) -> String {
+        let processed = processed.min(self.total);
+        let remaining = self.total - processed;
+
+        // Assume that the remaining items are going to be processed at the average rate
+        let eta = if processed == 0 || remaining == 0 {
+            None
+        } else {
+            let elapsed = now.saturating_sub(self.start_time).as_secs_f64();
+            Some(Duration::from_secs_f64(elapsed * remaining as f64 / processed as f64))
+        };
+
+        messages::get().progress(processed, self.total, eta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::tests::freeze;
+
+    use super::*;
+
+    #[test]
+    fn eta() {
+        let clock = freeze();
+        let progress = ProgressLogger::new(100, Duration::from_secs(10));
+        let message = |processed, eta| messages::get().progress(processed, 100, eta);
+
+        assert_eq!(progress.update(10), None);
+        clock.advance(Duration::from_secs(10));
+
+        let (processed, now) = progress.update(15).unwrap();
+        assert_eq!(progress.message(processed, now), message(25, Some(Duration::from_secs(30))));
+
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(progress.update(25), None);
+
+        clock.advance(Duration::from_secs(5));
+        let (processed, now) = progress.update(0).unwrap();
+        assert_eq!(progress.message(processed, now), message(50, Some(Duration::from_secs(20))));
+    }
+
+    #[test]
+    fn completion() {
+        let clock = freeze();
+        let progress = ProgressLogger::new(100, Duration::from_secs(10));
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(progress.update(50), None);
+
+        // The completion is logged regardless of the interval, but only once
+        let (processed, now) = progress.update(60).unwrap();
+        assert_eq!(progress.message(processed, now), messages::get().progress(100, 100, None));
+        assert_eq!(progress.update(1), None);
+    }
+}
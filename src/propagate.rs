@@ -0,0 +1,113 @@
+use std::env;
+use std::process::Command;
+
+use crate::context::GlobalContext;
+
+// Propagates the operation context to child processes via environment variable in
+// `@context=name,field=value` form with `%`, `,` and `=` percent-encoded.
+const CONTEXT_VARIABLE: &str = "EASY_LOG_CONTEXT";
+
+const CONTEXT_KEY: &str = "@context";
+
+pub(crate) fn export(command: &mut Command, static_fields: &[(String, String)]) {
+    let names = GlobalContext::get_names();
+
+    let entries: Vec<String> = names.iter().map(|name| (CONTEXT_KEY, name.as_str()))
+        .chain(static_fields.iter().map(|(name, value)| (name.as_str(), value.as_str())))
+        .map(|(key, value)| format!("{}={}", encode(key), encode(value)))
+        .collect();
+
+    if entries.is_empty() {
+        command.env_remove(CONTEXT_VARIABLE);
+    } else {
+        command.env(CONTEXT_VARIABLE, entries.join(","));
+    }
+}
+
+// The context inherited from the parent process
+#[derive(Default)]
+pub(crate) struct Context {
+    pub names: Vec<String>,
+    pub fields: Vec<(String, String)>,
+    // Malformed entries which have been skipped
+    pub invalid: Vec<String>,
+}
+
+pub(crate) fn import() -> Context {
+    let mut context = Context::default();
+
+    let Ok(value) = env::var(CONTEXT_VARIABLE) else {
+        return context;
+    };
+
+    for entry in value.split(',').filter(|entry| !entry.is_empty()) {
+        let Some((key, value)) = entry.split_once('=') else {
+            context.invalid.push(entry.to_owned());
+            continue;
+        };
+        let (key, value) = (decode(key), decode(value));
+
+        if key == CONTEXT_KEY {
+            context.names.push(value);
+        } else {
+            context.fields.push((key, value));
+        }
+    }
+
+    context
+}
+
+fn encode(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '%' | ',' | '=' => result.push_str(&format!("%{:02X}", c as u8)),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+fn decode(value: &str) -> String {
+    let mut result = Vec::with_capacity(value.len());
+    let bytes = value.as_bytes();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let code = bytes.get(index + 1..index + 3)
+            .and_then(|code| std::str::from_utf8(code).ok())
+            .and_then(|code| u8::from_str_radix(code, 16).ok());
+
+        match code {
+            Some(code) if bytes[index] == b'%' => {
+                result.push(code);
+                index += 3;
+            },
+            _ => {
+                result.push(bytes[index]);
+                index += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        for value in ["", "plain value", "a=b,c=d", "100%", "%2C", "юникод=%"] {
+            let encoded = encode(value);
+            assert!(!encoded.contains([',', '=']), "{encoded:?}");
+            assert_eq!(decode(&encoded), value);
+        }
+
+        assert_eq!(encode("a=b,c%"), "a%3Db%2Cc%25");
+        assert_eq!(decode("%zz%4"), "%zz%4");
+    }
+}
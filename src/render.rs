@@ -0,0 +1,125 @@
+use chrono::{DateTime, FixedOffset};
+use log::Level;
+
+use crate::encoder::{EncodeContext, RecordData, SourceLocation, TextEncoder};
+use crate::pipeline::MessageOptions;
+
+/// A log record detached from the logger (parsed from a log file for example) which can be
+/// rendered by [`format_record()`].
+#[derive(Clone, Debug)]
+pub struct LogRecordData {
+    pub time: DateTime<FixedOffset>,
+    pub level: Level,
+    pub target: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// Context names of the record (see [`GlobalContext`](crate::GlobalContext))
+    pub context: Vec<String>,
+    pub message: String,
+}
+
+/// Options of [`format_record()`] which correspond to the logger configuration.
+#[derive(Clone)]
+pub struct FormatOptions {
+    encoder: TextEncoder,
+    colored: bool,
+    source_location: SourceLocation,
+    static_fields: Vec<(String, String)>,
+    terminal_width: Option<usize>,
+    message: MessageOptions,
+}
+
+impl FormatOptions {
+    pub fn new() -> FormatOptions {
+        FormatOptions {
+            encoder: TextEncoder::new(),
+            colored: false,
+            source_location: SourceLocation::default(),
+            static_fields: Vec::new(),
+            terminal_width: None,
+            message: MessageOptions::default(),
+        }
+    }
+
+    /// Sets the encoder which defines the layout (the default one is used by file sinks).
+    pub fn encoder(mut self, encoder: TextEncoder) -> Self {
+        self.encoder = encoder;
+        self
+    }
+
+    pub fn colored(mut self) -> Self {
+        self.colored = true;
+        self
+    }
+
+    pub fn source_location(mut self, location: SourceLocation) -> Self {
+        self.source_location = location.resolve();
+        self
+    }
+
+    /// Sets the fields rendered by [`TextEncoder::static_fields()`].
+    pub fn static_fields(mut self, fields: &[(&str, &str)]) -> Self {
+        self.static_fields.extend(fields.iter().map(|&(name, value)| (name.to_owned(), value.to_owned())));
+        self
+    }
+
+    /// Truncates the messages as [`LoggingConfig::max_message_length()`] does.
+    ///
+    /// [`LoggingConfig::max_message_length()`]: crate::LoggingConfig::max_message_length
+    pub fn max_message_length(mut self, length: usize) -> Self {
+        self.message.max_length = Some(length);
+        self
+    }
+
+    /// Tags records with unknown targets as [`LoggingConfig::tag_unknown_targets()`] does.
+    ///
+    /// [`LoggingConfig::tag_unknown_targets()`]: crate::LoggingConfig::tag_unknown_targets
+    pub fn tag_unknown_targets(mut self) -> Self {
+        self.message.tag_unknown_targets = true;
+        self
+    }
+
+    /// Adapts column widths to the specified terminal width as the console sink does.
+    pub fn terminal_width(mut self, width: usize) -> Self {
+        self.terminal_width = Some(width);
+        self
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions::new()
+    }
+}
+
+/// Renders the record exactly as the live logger renders it with [`TextEncoder`] (including message
+/// escaping, truncation and tagging), so external tools (log viewers for example) don't have to
+/// reimplement the format. The result includes the trailing newline.
+pub fn format_record(record: &LogRecordData, options: &FormatOptions) -> String {
+    let message = options.message.format(&record.target, format_args!("{}", record.message));
+
+    let context = EncodeContext {
+        colored: options.colored,
+        max_level: Level::Trace,
+        static_fields: &options.static_fields,
+        source_location: options.source_location,
+        time_zone: Default::default(),
+        time: record.time,
+        terminal_width: options.terminal_width,
+    };
+
+    let record = RecordData {
+        time: record.time,
+        level: record.level,
+        target: &record.target,
+        file: record.file.as_deref(),
+        line: record.line,
+        context: record.context.iter().map(|name| format!("[{name}] ")).collect(),
+        style: None,
+        message: &message,
+    };
+
+    let mut buf = Vec::new();
+    let _ = options.encoder.render(&record, &context, &mut buf);
+    String::from_utf8_lossy(&buf).into_owned()
+}
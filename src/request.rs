@@ -0,0 +1,80 @@
+use std::fmt::{self, Display, Formatter};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use http::Request;
+use tokio::task::futures::TaskLocalFuture;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::clock;
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: RequestContext;
+}
+
+/// Tower layer which renders the context of the HTTP request being handled (`[GET /api/users 45ms]`)
+/// in all records logged by its task.
+#[derive(Clone, Default)]
+pub struct RequestContextLayer {
+}
+
+impl RequestContextLayer {
+    pub fn new() -> RequestContextLayer {
+        RequestContextLayer {}
+    }
+}
+
+impl<S> Layer<S> for RequestContextLayer {
+    type Service = RequestContextService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestContextService {inner}
+    }
+}
+
+/// Service created by [`RequestContextLayer`].
+#[derive(Clone)]
+pub struct RequestContextService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<Request<B>> for RequestContextService<S> where S: Service<Request<B>> {
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TaskLocalFuture<RequestContext, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let context = RequestContext {
+            method: request.method().to_string(),
+            path: request.uri().path().to_owned(),
+            start_time: clock::monotonic(),
+        };
+
+        let future = REQUEST_CONTEXT.sync_scope(context.clone(), || self.inner.call(request));
+        REQUEST_CONTEXT.scope(context, future)
+    }
+}
+
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct RequestContext {
+    method: String,
+    path: String,
+    start_time: Duration,
+}
+
+impl Display for RequestContext {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let duration = crate::fmt::duration(clock::elapsed(self.start_time));
+        write!(f, "{} {} {duration}", self.method, self.path)
+    }
+}
+
+pub(crate) fn get() -> Option<String> {
+    REQUEST_CONTEXT.try_with(ToString::to_string).ok()
+}
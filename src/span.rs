@@ -0,0 +1,88 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use log::{Level, Record};
+
+use crate::clock;
+use crate::messages;
+
+/// Logs the beginning of an operation on creation and its end (with duration) on drop.
+///
+/// Created by [`span!`](crate::span!) macro.
+#[must_use = "the span ends immediately if it's not bound to a variable"]
+pub struct Span {
+    level: Level,
+    target: &'static str,
+    file: &'static str,
+    line: u32,
+    name: String,
+    start_time: Duration,
+    error: Option<String>,
+}
+
+impl Span {
+    #[doc(hidden)]
+    pub fn new(level: Level, target: &'static str, file: &'static str, line: u32, name: String) -> Span {
+        let span = Span {
+            level, target, file, line, name,
+            start_time: clock::monotonic(),
+            error: None,
+        };
+        span.log(|| messages::get().span_started(&span.name));
+        span
+    }
+
+    /// Marks the operation as failed: it will be reported as failed with the specified error.
+    pub fn fail<E: Display>(&mut self, error: E) {
+        self.error = Some(error.to_string());
+    }
+
+    fn log<F: FnOnce() -> String>(&self, get_message: F) {
+        if crate::enabled(self.level, self.target) {
+            let message = get_message();
+            log::logger().log(&Record::builder()
+                .level(self.level)
+                .target(self.target)
+                .module_path_static(Some(self.target))
+                .file_static(Some(self.file))
+                .line(Some(self.line))
+                .args(format_args!("{message}"))
+                .build());
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if self.level > log::STATIC_MAX_LEVEL {
+            return;
+        }
+
+        let duration = clock::elapsed(self.start_time);
+
+        match self.error {
+            Some(ref error) => self.log(|| messages::get().span_failed(&self.name, duration, error)),
+            None => self.log(|| messages::get().span_finished(&self.name, duration)),
+        }
+    }
+}
+
+/// Creates a [`Span`] which logs "... started" message now and "... finished in 3.2s" (or "...
+/// failed in 3.2s: error" if [`Span::fail()`] has been called) when it's dropped. Both messages
+/// are logged with the specified level. The messages can be localized via [`Messages`](crate::Messages).
+#[macro_export]
+macro_rules! span {
+    (target: $target:expr, $level:expr, $($arg:tt)+) => {{
+        let level = $level;
+        // Don't format the name for levels disabled at compile time
+        let name = if level <= $crate::log::STATIC_MAX_LEVEL {
+            format!($($arg)+)
+        } else {
+            String::new()
+        };
+        $crate::Span::new(level, $target, file!(), line!(), name)
+    }};
+    ($level:expr, $($arg:tt)+) => {
+        $crate::span!(target: module_path!(), $level, $($arg)+)
+    };
+}
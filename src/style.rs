@@ -0,0 +1,28 @@
+use std::cell::Cell;
+
+use ansi_term::Style;
+
+thread_local! {
+    static STYLE: Cell<Option<Style>> = const { Cell::new(None) };
+}
+
+/// Forces the style for all records logged by the closure in the current thread when output is
+/// colored, so important messages can stand out regardless of their level:
+///
+/// `easy_logging::styled(Style::new().bold(), || info!("IMPORTANT"))`
+pub fn styled<F: FnOnce() -> R, R>(style: Style, f: F) -> R {
+    struct Guard(Option<Style>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            STYLE.with(|current| current.set(self.0));
+        }
+    }
+
+    let _guard = Guard(STYLE.with(|current| current.replace(Some(style))));
+    f()
+}
+
+pub(crate) fn get_style() -> Option<Style> {
+    STYLE.with(Cell::get)
+}
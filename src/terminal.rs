@@ -0,0 +1,67 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// The width is re-queried periodically instead of watching SIGWINCH to not interfere with the
+// signal handlers of the application
+const REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+// A terminal output the records are written to
+#[derive(Clone, Copy)]
+pub(crate) enum Stream {
+    Stdout,
+    Stderr,
+}
+
+// Terminal width cached for the refresh interval (0 means unknown)
+struct CachedWidth {
+    width: AtomicUsize,
+    // Milliseconds since the first query offset by one (0 means not queried yet)
+    query_time: AtomicU64,
+}
+
+static CACHE: [CachedWidth; 2] = [
+    CachedWidth {width: AtomicUsize::new(0), query_time: AtomicU64::new(0)},
+    CachedWidth {width: AtomicUsize::new(0), query_time: AtomicU64::new(0)},
+];
+
+// Returns width of the terminal the stream is written to
+pub(crate) fn width(stream: Stream) -> Option<usize> {
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    let cache = &CACHE[stream as usize];
+    let now = START.get_or_init(Instant::now).elapsed().as_millis() as u64 + 1;
+    let query_time = cache.query_time.load(Ordering::Relaxed);
+
+    if query_time == 0 || now.saturating_sub(query_time) >= REFRESH_INTERVAL.as_millis() as u64 {
+        cache.width.store(get_width(stream).unwrap_or(0), Ordering::Relaxed);
+        cache.query_time.store(now, Ordering::Relaxed);
+    }
+
+    match cache.width.load(Ordering::Relaxed) {
+        0 => None,
+        width => Some(width),
+    }
+}
+
+#[cfg(unix)]
+fn get_width(stream: Stream) -> Option<usize> {
+    let fd = match stream {
+        Stream::Stdout => libc::STDOUT_FILENO,
+        Stream::Stderr => libc::STDERR_FILENO,
+    };
+
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_col != 0 {
+            return Some(size.ws_col.into());
+        }
+    }
+
+    None
+}
+
+#[cfg(not(unix))]
+fn get_width(_stream: Stream) -> Option<usize> {
+    None
+}
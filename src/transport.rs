@@ -0,0 +1,503 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+use crate::buffer::ErrorContextBuffer;
+use crate::clock;
+use crate::encoder::{EncodeContext, Encoder, SourceLocation, TimeZone};
+use crate::environment::Environment;
+use crate::fork;
+use crate::handle::State;
+use crate::heartbeat::Heartbeat;
+use crate::messages;
+use crate::terminal::{self, Stream};
+
+/// Transport defines where encoded log records are written to.
+pub trait Transport: Send + Sync {
+    /// Whether records of the specified level are going to be written to a terminal which
+    /// supports colors.
+    fn colored(&self, _level: Level) -> bool {
+        false
+    }
+
+    /// Width of the terminal records of the specified level are written to (if any), so the
+    /// encoder can adapt its layout to it.
+    fn terminal_width(&self, _level: Level) -> Option<usize> {
+        None
+    }
+
+    /// Writes a single encoded record.
+    fn write(&self, level: Level, data: &[u8]) -> io::Result<()>;
+
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Reopens the underlying resources (for example after log rotation).
+    fn reopen(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Sink configuration before logger initialization
+pub(crate) struct SinkConfig {
+    pub transport: Arc<dyn Transport>,
+    // The default one is used if not specified
+    pub encoder: Option<Box<dyn Encoder>>,
+    pub level: LevelFilter,
+    pub error_context: Option<Arc<ErrorContextBuffer>>,
+    pub heartbeat: Option<Duration>,
+    // The logger default is used if not specified
+    pub time_zone: Option<TimeZone>,
+    // Receives only audit records
+    pub audit: bool,
+    // Is silenced together with the logger even for the records which bypass the level filters
+    pub console: bool,
+}
+
+impl SinkConfig {
+    pub fn new(transport: Arc<dyn Transport>, encoder: Option<Box<dyn Encoder>>) -> SinkConfig {
+        SinkConfig {
+            transport, encoder,
+            level: LevelFilter::Trace,
+            error_context: None,
+            heartbeat: None,
+            time_zone: None,
+            audit: false,
+            console: false,
+        }
+    }
+}
+
+// Binds an encoder to a transport
+pub(crate) struct Sink {
+    encoder: Box<dyn Encoder>,
+    transport: Arc<dyn Transport>,
+    level: LevelFilter,
+    console: bool,
+    error_context: Option<Arc<ErrorContextBuffer>>,
+    heartbeat: Option<Heartbeat>,
+    time_zone: TimeZone,
+    settings: Arc<SinkSettings>,
+    // Fork generation the transport has been opened in
+    fork_generation: AtomicU64,
+}
+
+// Settings shared by all sinks
+pub(crate) struct SinkSettings {
+    pub max_level: Level,
+    pub static_fields: Vec<(String, String)>,
+    pub source_location: SourceLocation,
+    pub time_zone: TimeZone,
+    pub state: Arc<State>,
+}
+
+impl Sink {
+    pub fn new(config: SinkConfig, encoder: Box<dyn Encoder>, settings: Arc<SinkSettings>) -> Sink {
+        Sink {
+            encoder,
+            transport: config.transport,
+            level: config.level,
+            console: config.console,
+            error_context: config.error_context,
+            heartbeat: config.heartbeat.map(Heartbeat::new),
+            time_zone: config.time_zone.unwrap_or(settings.time_zone),
+            settings,
+            fork_generation: AtomicU64::new(fork::generation()),
+        }
+    }
+
+    pub fn heartbeat(&self) -> Option<&Heartbeat> {
+        self.heartbeat.as_ref()
+    }
+
+    // Writes the record bypassing all sink logic and activity tracking
+    pub fn write_unobserved(&self, record: &Record) {
+        if let Some(data) = self.encode(record) {
+            self.reopen_after_fork();
+            let _ = self.transport.write(record.level(), &data);
+        }
+    }
+
+    // The transport isn't reopened by the fork handler since it can't allocate and open files
+    fn reopen_after_fork(&self) {
+        let generation = fork::generation();
+        if self.fork_generation.swap(generation, Ordering::Relaxed) != generation {
+            let _ = self.transport.reopen();
+        }
+    }
+
+    fn write(&self, level: Level, data: &[u8]) {
+        self.reopen_after_fork();
+        let _ = self.transport.write(level, data);
+        if let Some(ref heartbeat) = self.heartbeat {
+            heartbeat.touch();
+        }
+    }
+
+    fn encode(&self, record: &Record) -> Option<Vec<u8>> {
+        let context = EncodeContext {
+            colored: self.transport.colored(record.level()),
+            max_level: self.settings.max_level,
+            static_fields: &self.settings.static_fields,
+            source_location: self.settings.source_location,
+            time_zone: self.time_zone,
+            time: self.time_zone.convert(clock::now()),
+            terminal_width: self.transport.terminal_width(record.level()),
+        };
+
+        let mut buf = Vec::new();
+        self.encoder.encode(record, &context, &mut buf).ok()?;
+        Some(buf)
+    }
+}
+
+impl Log for Sink {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Audit records bypass the logger filter, so the console must be silenced on its own to not
+        // break the interactive prompts
+        metadata.level() <= self.level && !(self.console && self.settings.state.levels.is_silenced())
+    }
+
+    fn log(&self, record: &Record) {
+        let level = record.level();
+
+        if let Some(ref error_context) = self.error_context {
+            if level >= Level::Debug {
+                let message = messages::get().preceding_context(&record.args().to_string());
+                let data = self.encode(&Record::builder()
+                    .metadata(record.metadata().clone())
+                    .module_path(record.module_path())
+                    .file(record.file())
+                    .line(record.line())
+                    .args(format_args!("{message}"))
+                    .build());
+
+                if let Some(data) = data {
+                    error_context.push(level, data);
+                }
+                return;
+            }
+
+            if level == Level::Error {
+                for (level, data) in error_context.take() {
+                    self.write(level, &data);
+                }
+            }
+        }
+
+        if let Some(data) = self.encode(record) {
+            self.write(level, &data);
+        }
+    }
+
+    fn flush(&self) {
+        self.reopen_after_fork();
+        let _ = self.transport.flush();
+    }
+}
+
+lazy_static! {
+    // Since we write into stdout and stderr we should guard any write with a mutex to not get the
+    // output interleaved. It's also shared with the raw stdout writer.
+    pub(crate) static ref OUTPUT_MUTEX: Mutex<()> = Mutex::new(());
+}
+
+// Writes records of the split level and more severe ones to stderr and all others to stdout
+pub(crate) struct ConsoleTransport {
+    output: ConsoleOutput,
+    split_level: Level,
+    stdout_colored: bool,
+    stderr_colored: bool,
+    stdout_tty: bool,
+    stderr_tty: bool,
+}
+
+enum ConsoleOutput {
+    Std,
+    // stdout and stderr refer to the same file, pipe or journal, so write all records to stdout to
+    // preserve their order
+    Merged,
+    Custom(Mutex<ConsoleWriters>),
+}
+
+pub(crate) struct ConsoleWriters {
+    pub stdout: Box<dyn Write + Send>,
+    pub stderr: Box<dyn Write + Send>,
+    pub colored: bool,
+}
+
+impl ConsoleTransport {
+    pub fn new(writers: Option<ConsoleWriters>, split_level: Level, environment: &Environment) -> ConsoleTransport {
+        match writers {
+            Some(writers) => ConsoleTransport {
+                split_level,
+                stdout_colored: writers.colored,
+                stderr_colored: writers.colored,
+                stdout_tty: false,
+                stderr_tty: false,
+                output: ConsoleOutput::Custom(Mutex::new(writers)),
+            },
+            None if environment.merged_output() => {
+                let colored = environment.stdout_colored();
+                ConsoleTransport {
+                    output: ConsoleOutput::Merged,
+                    split_level,
+                    stdout_colored: colored,
+                    stderr_colored: colored,
+                    // The outputs can be merged only if they aren't terminals
+                    stdout_tty: false,
+                    stderr_tty: false,
+                }
+            },
+            None => ConsoleTransport {
+                output: ConsoleOutput::Std,
+                split_level,
+                stdout_colored: environment.stdout_colored(),
+                stderr_colored: environment.stderr_colored(),
+                stdout_tty: environment.stdout_tty,
+                stderr_tty: environment.stderr_tty,
+            },
+        }
+    }
+
+    fn is_stderr(&self, level: Level) -> bool {
+        // Please note that more severe levels are "less" than others: Error < Warn < Info
+        level <= self.split_level
+    }
+}
+
+impl Transport for ConsoleTransport {
+    fn colored(&self, level: Level) -> bool {
+        if self.is_stderr(level) {
+            self.stderr_colored
+        } else {
+            self.stdout_colored
+        }
+    }
+
+    fn terminal_width(&self, level: Level) -> Option<usize> {
+        let (tty, stream) = if self.is_stderr(level) {
+            (self.stderr_tty, Stream::Stderr)
+        } else {
+            (self.stdout_tty, Stream::Stdout)
+        };
+
+        if tty {
+            terminal::width(stream)
+        } else {
+            None
+        }
+    }
+
+    fn write(&self, level: Level, data: &[u8]) -> io::Result<()> {
+        match self.output {
+            ConsoleOutput::Std => {
+                let _lock = OUTPUT_MUTEX.lock();
+
+                if self.is_stderr(level) {
+                    write_record(&mut io::stderr().lock(), data)
+                } else {
+                    write_record(&mut io::stdout().lock(), data)
+                }
+            },
+            ConsoleOutput::Merged => {
+                let _lock = OUTPUT_MUTEX.lock();
+                write_record(&mut io::stdout().lock(), data)
+            },
+            ConsoleOutput::Custom(ref writers) => {
+                let mut writers = writers.lock().unwrap();
+
+                if self.is_stderr(level) {
+                    write_record(&mut writers.stderr, data)
+                } else {
+                    write_record(&mut writers.stdout, data)
+                }
+            },
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.output {
+            ConsoleOutput::Std | ConsoleOutput::Merged => {
+                io::stdout().flush()?;
+                io::stderr().flush()
+            },
+            ConsoleOutput::Custom(ref writers) => {
+                let mut writers = writers.lock().unwrap();
+                writers.stdout.flush()?;
+                writers.stderr.flush()
+            },
+        }
+    }
+}
+
+fn write_record<W: Write + ?Sized>(writer: &mut W, data: &[u8]) -> io::Result<()> {
+    writer.write_all(data)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{AUDIT_TARGET, Capture, EnvironmentOverrides, FileSink, LoggingConfig, TextEncoder};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Output {
+        fn lines(&self) -> Vec<String> {
+            let data = self.0.lock().unwrap();
+            String::from_utf8_lossy(&data).lines().map(ToOwned::to_owned).collect()
+        }
+    }
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn route(split_level: Option<Level>) -> (Vec<String>, Vec<String>) {
+        let (stdout, stderr) = (Output::default(), Output::default());
+
+        let mut config = LoggingConfig::new("test", Level::Trace)
+            .console_writers(stdout.clone(), stderr.clone(), false);
+        if let Some(level) = split_level {
+            config = config.split_at(level);
+        }
+        let logger = config.logger();
+
+        for level in Level::iter() {
+            logger.log(&Record::builder()
+                .level(level)
+                .target("test")
+                .args(format_args!("{level} message"))
+                .build());
+        }
+
+        let messages = |output: Output| output.lines().iter().filter_map(|line| {
+            line.split_once(": ").map(|(_, message)| message.to_owned())
+        }).collect::<Vec<_>>();
+
+        (messages(stdout), messages(stderr))
+    }
+
+    #[test]
+    fn split() {
+        let environment = EnvironmentOverrides::new()
+            .stdout_tty(false).stderr_tty(false).systemd(false).same_output(false)
+            .resolve();
+
+        let transport = ConsoleTransport::new(None, Level::Warn, &environment);
+        assert!(transport.is_stderr(Level::Error));
+        assert!(transport.is_stderr(Level::Warn));
+        assert!(!transport.is_stderr(Level::Info));
+        assert!(!transport.is_stderr(Level::Trace));
+    }
+
+    #[test]
+    fn routing() {
+        assert_eq!(route(None), (
+            vec!["INFO message".to_owned(), "DEBUG message".to_owned(), "TRACE message".to_owned()],
+            vec!["ERROR message".to_owned(), "WARN message".to_owned()],
+        ));
+
+        assert_eq!(route(Some(Level::Error)), (
+            vec!["WARN message".to_owned(), "INFO message".to_owned(), "DEBUG message".to_owned(),
+                 "TRACE message".to_owned()],
+            vec!["ERROR message".to_owned()],
+        ));
+
+        assert_eq!(route(Some(Level::Trace)), (
+            vec![],
+            vec!["ERROR message".to_owned(), "WARN message".to_owned(), "INFO message".to_owned(),
+                 "DEBUG message".to_owned(), "TRACE message".to_owned()],
+        ));
+    }
+
+    #[test]
+    fn text_static_fields() {
+        let log = |config: LoggingConfig| {
+            let output = Output::default();
+            let logger = config
+                .static_fields(&[("service", "billing"), ("env", "prod")])
+                .console_writers(output.clone(), output.clone(), false)
+                .logger();
+
+            logger.log(&Record::builder().level(Level::Info).target("test").args(format_args!("message")).build());
+            output.lines()
+        };
+
+        assert_eq!(log(LoggingConfig::new("test", Level::Info)), vec!["I: message".to_owned()]);
+        assert_eq!(log(LoggingConfig::new("test", Level::Info).text_static_fields()),
+                   vec!["I: [service=billing env=prod] message".to_owned()]);
+    }
+
+    #[test]
+    fn error_context() {
+        let path = std::env::temp_dir().join(format!("easy-logging-error-context-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (output, capture) = (Output::default(), Capture::new());
+        let config = LoggingConfig::new("test", Level::Info)
+            .level_for("other", LevelFilter::Warn)
+            .console_writers(output.clone(), output.clone(), false)
+            .file(FileSink::new(&path).encoder(TextEncoder::new().compact()).error_context(Duration::from_secs(60), 10))
+            .capture(&capture);
+        let handle = config.handle();
+        let logger = config.logger();
+
+        for (level, target) in [
+            (Level::Debug, "test"), (Level::Info, "test"), (Level::Debug, "other"), (Level::Info, "other"),
+            (Level::Debug, "disabled"), (Level::Error, "test"),
+        ] {
+            let metadata = Metadata::builder().level(level).target(target).build();
+            if logger.enabled(&metadata) {
+                logger.log(&Record::builder().metadata(metadata).args(format_args!("{level} {target}")).build());
+            }
+        }
+
+        let file = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        // Only Debug and Trace records of the enabled targets are captured regardless of their level
+        assert_eq!(file.lines().collect::<Vec<_>>(), vec![
+            "I: INFO test", "D: preceding context: DEBUG test", "D: preceding context: DEBUG other",
+            "E: ERROR test",
+        ]);
+        assert_eq!(output.lines(), vec!["I: INFO test", "E: ERROR test"]);
+
+        assert_eq!(capture.records().len(), 2);
+        assert_eq!(handle.execute("stats").unwrap(), "error=1 warn=0 info=1 debug=0 trace=0");
+    }
+
+    #[test]
+    fn silenced_audit() {
+        let output = Output::default();
+        let config = LoggingConfig::new("test", Level::Info).console_writers(output.clone(), output.clone(), false);
+        let handle = config.handle();
+        let logger = config.logger();
+
+        let log = |message: &str| logger.log(&Record::builder()
+            .level(Level::Info).target(AUDIT_TARGET).args(format_args!("{message}")).build());
+
+        handle.silence();
+        log("silenced");
+        handle.unsilence();
+        log("unsilenced");
+
+        assert_eq!(output.lines(), vec!["I: unsilenced".to_owned()]);
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{self, StdoutLock, Write};
+use std::sync::MutexGuard;
+
+use log::{Level, Record};
+
+use crate::transport::OUTPUT_MUTEX;
+
+/// Returns a writer which turns each written line into a log record with the specified level and
+/// target. Useful for libraries which accept only an `io::Write` for their diagnostics output.
+pub fn writer(level: Level, target: &str) -> LogWriter {
+    LogWriter {
+        level,
+        target: target.to_owned(),
+        buf: Vec::new(),
+    }
+}
+
+/// A writer returned by [`writer()`].
+///
+/// An incomplete line is kept until it's terminated by a newline or the writer is dropped.
+pub struct LogWriter {
+    level: Level,
+    target: String,
+    buf: Vec<u8>,
+}
+
+impl LogWriter {
+    fn log_line(&self, line: &[u8]) {
+        let line = String::from_utf8_lossy(line);
+
+        if crate::enabled(self.level, &self.target) {
+            log::logger().log(&Record::builder()
+                .level(self.level)
+                .target(&self.target)
+                .args(format_args!("{line}"))
+                .build());
+        }
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        for line in take_lines(&mut self.buf, data) {
+            self.log_line(&line);
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        if let Some(line) = take_rest(&mut self.buf) {
+            self.log_line(&line);
+        }
+    }
+}
+
+// Appends the data to the buffer and takes all complete lines from it
+fn take_lines(buf: &mut Vec<u8>, data: &[u8]) -> Vec<Vec<u8>> {
+    buf.extend_from_slice(data);
+
+    let Some(end) = buf.iter().rposition(|&byte| byte == b'\n') else {
+        return Vec::new();
+    };
+
+    let lines: Vec<u8> = buf.drain(..=end).collect();
+    lines[..end].split(|&byte| byte == b'\n').map(|line| strip_cr(line).to_vec()).collect()
+}
+
+// Takes the incomplete last line
+fn take_rest(buf: &mut Vec<u8>) -> Option<Vec<u8>> {
+    if buf.is_empty() {
+        return None;
+    }
+    let line = std::mem::take(buf);
+    Some(strip_cr(&line).to_vec())
+}
+
+fn strip_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Returns a writer to stdout which is synchronized with the console sink, so non-log output
+/// (tables, prompts, progress, etc.) doesn't interleave with concurrently emitted records.
+///
+/// Each write is flushed immediately to not leave a partial line for the logger to append to.
+pub fn raw_stdout() -> RawStdout {
+    RawStdout {}
+}
+
+/// A writer returned by [`raw_stdout()`].
+pub struct RawStdout {
+}
+
+impl RawStdout {
+    /// Blocks the console output until the returned guard is dropped, so several writes are
+    /// emitted together. Logging from the current thread while the guard is held deadlocks.
+    pub fn lock(&self) -> RawStdoutLock<'static> {
+        // Lock in the same order as the console sink does
+        let guard = OUTPUT_MUTEX.lock().unwrap_or_else(|err| err.into_inner());
+        RawStdoutLock {stdout: io::stdout().lock(), _guard: guard}
+    }
+}
+
+impl Write for RawStdout {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut stdout = self.lock();
+        stdout.write_all(data)?;
+        stdout.flush()?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// Exclusive access to stdout returned by [`RawStdout::lock()`]. Flushed on drop.
+pub struct RawStdoutLock<'a> {
+    stdout: StdoutLock<'a>,
+    _guard: MutexGuard<'a, ()>,
+}
+
+impl Write for RawStdoutLock<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.stdout.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+impl Drop for RawStdoutLock<'_> {
+    fn drop(&mut self) {
+        let _ = self.stdout.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(buf: &mut Vec<u8>, data: &str) -> Vec<String> {
+        take_lines(buf, data.as_bytes()).iter().map(|line| String::from_utf8_lossy(line).into_owned()).collect()
+    }
+
+    #[test]
+    fn line_splitting() {
+        let mut buf = Vec::new();
+
+        assert_eq!(lines(&mut buf, "first\nsecond\r\n\nthird"), vec!["first", "second", ""]);
+        assert_eq!(lines(&mut buf, " line"), Vec::<String>::new());
+        assert_eq!(lines(&mut buf, " continued\nfourth"), vec!["third line continued"]);
+
+        assert_eq!(take_rest(&mut buf), Some(b"fourth".to_vec()));
+        assert_eq!(take_rest(&mut buf), None);
+    }
+
+    #[test]
+    fn partial_last_line() {
+        let mut buf = Vec::new();
+
+        assert_eq!(lines(&mut buf, "partial\r"), Vec::<String>::new());
+        assert_eq!(take_rest(&mut buf), Some(b"partial".to_vec()));
+
+        assert_eq!(lines(&mut buf, "\n"), vec![""]);
+        assert_eq!(take_rest(&mut buf), None);
+    }
+}